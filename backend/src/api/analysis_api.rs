@@ -0,0 +1,31 @@
+use crate::{
+    models::{
+        analysis_model::*,
+        config_model::AppConfig
+    },
+    services::analysis::solve
+};
+
+use rocket::{
+    http::Status,
+    serde::json::Json,
+    State
+};
+
+/*
+Let the engine look at any position without storing a board.
+ */
+#[post("/analyze", data = "<req>")]
+pub fn analyze_position(config: &State<AppConfig>, req: Json<AnalyzeRequest>) -> Result<Json<AnalyzeResponse>, Status> {
+
+    let depth = match req.depth {
+        0 => DEFAULT_ANALYZE_DEPTH,
+        d if 0 < d && d <= MAX_ANALYZE_DEPTH => d,
+        _ => return Ok(Json(AnalyzeResponse::failure("Depth must be between 1 and 10."))),
+    };
+
+    match solve(&req.position, depth, config.search_threads) {
+        Ok(res) => Ok(Json(res)),
+        Err(msg) => Ok(Json(AnalyzeResponse::failure(&msg))),
+    }
+}
@@ -1,9 +1,18 @@
 use crate::{
+    api::user_api::login,
     models::{
         board_model::*,
+        config_model::AppConfig,
+        event_model::GameEvent,
         general_model::GeneralStatus
     },
-    repository::{board_repo::BoardRepo, hist_repo::HistRepo, user_repo::UserRepo}
+    repository::{board_repo::BoardRepo, hist_repo::HistRepo, user_repo::UserRepo},
+    services::{
+        engine::EnginePool,
+        game_end::GameEnd,
+        game_hub::GameHub,
+        sessions::Sessions
+    }
 };
 
 use rocket::{
@@ -12,51 +21,169 @@ use rocket::{
     State
 };
 
-use rand::seq::SliceRandom;
+use chrono::prelude::*;
 
 extern crate argon2;
 
 const COMPUTER_STR: &str = "*";
 const DRAW_STR: &str = "^";
 
+/*
+Store a fresh board for a new game and let both players know about it.
+Every way of starting a game goes through here so that new boards are set up the same way.
+ */
+pub fn start_board(db: &BoardRepo, hub: &GameHub, board: &Board) -> Result<Board, Box<Board>> {
+    open_board(db, hub, new_game(board))
+}
+
+// an empty board with the settings of the given one
+fn new_game(board: &Board) -> Board {
+    let mut board_var = Board::new(board.width, board.height, board.player_1.clone(), board.player_2.clone(), board.mode.clone(), board.difficulty);
+    board_var.time_control = board.time_control.clone();
+    board_var
+}
+
+fn open_board(db: &BoardRepo, hub: &GameHub, mut board_var: Board) -> Result<Board, Box<Board>> {
+
+    board_var.start_clocks();
+    if !db.create_board(board_var.clone()) {
+        return Err(Box::new(board_var));
+    }
+
+    let started = GameEvent::GameStarted {
+        board_id: board_var.id.clone(),
+        player_1: board_var.player_1.clone(),
+        player_2: board_var.player_2.clone(),
+    };
+    hub.notify_user(&board_var.player_1, started.clone());
+    hub.notify_user(&board_var.player_2, started);
+    Ok(board_var)
+}
+
+/*
+Create a board, either empty or set up from a position string.
+The position decides the size and mode of the board.
+ */
 #[post("/board/create", data = "<new_board>")]
-pub fn create_board(db: &State<BoardRepo>, new_board: Json<Board>) -> Result<Json<GeneralBoardResponse>, Status> {
+pub fn create_board(db: &State<BoardRepo>, hub: &State<GameHub>, new_board: Json<CreateBoardRequest>) -> Result<Json<GeneralBoardResponse>, Status> {
 
-    let board_var = Board::new(new_board.width.clone(), new_board.height.clone(), new_board.player_1.clone(), new_board.player_2.clone(), new_board.mode.clone(), new_board.difficulty.clone());
-    match db.create_board(board_var.clone()) {
+    if !Board::valid_size(new_board.board.width, new_board.board.height) {
+        return Ok(Json(GeneralBoardResponse {
+            status: GeneralStatus::failure(&format!("The board must be between {0}x{0} and {1}x{1}.", MIN_SIZE, MAX_SIZE)),
+            board: Board::empty(),
+        }));
+    }
+    let mut board_var = new_game(&new_board.board);
+    if !new_board.position.is_empty() {
+        match board_var.set_position(&new_board.position) {
+            Ok(()) => (),
+            Err(msg) => return Ok(Json(GeneralBoardResponse {
+                status: GeneralStatus::failure(&msg),
+                board: Board::empty(),
+            })),
+        };
+    }
 
-        true => Ok(Json(GeneralBoardResponse {
+    match open_board(db, hub, board_var) {
+
+        Ok(board_var) => Ok(Json(GeneralBoardResponse {
             status: GeneralStatus::success(),
             board: board_var.clone(),
         })),
 
-        false => Ok(Json(GeneralBoardResponse {
+        Err(board_var) => Ok(Json(GeneralBoardResponse {
             status: GeneralStatus::failure("User already exists or database not connected."),
-            board: board_var.clone(),
+            board: *board_var,
         }))
     }
 }
 
+/*
+Move for the player in the request, or give up when the column is -1.
+The player is the one the login token was given to and may only move on their own turn,
+so when both players share one screen it is the player to move who sends their token.
+ */
 #[post("/board/move", data = "<move_req>")]
-pub fn perform_move(db: &State<BoardRepo>, move_req: Json<PerformMoveRequest>) -> Result<Json<PerformMoveResponse>, Status> {
+pub fn perform_move(db: &State<BoardRepo>, hist: &State<HistRepo>, users: &State<UserRepo>, sessions: &State<Sessions>, hub: &State<GameHub>, engine: &State<EnginePool>, move_req: Json<PerformMoveRequest>) -> Result<Json<PerformMoveResponse>, Status> {
+
+    let end = GameEnd { db, hist, users, hub, engine };
 
     let (board, col) = (
         move_req.board_info.clone(),
-        move_req.col.clone()
+        move_req.col
     );
 
+    let player = match sessions.name_of(&move_req.token) {
+        Some(name) => name,
+        None => return Ok(Json(PerformMoveResponse::new(
+            (false, "Please log in again."),
+            (-1, -1),
+            (-1, -1),
+            "".to_owned(),
+            "".to_owned(),
+            &Board::empty()
+        ))),
+    };
+
     match db.get_board(&board) {
         
         // there is a matched active board in database
         Some(mut b) => {
 
+            // only the two players of the board may move or give up on it
+            if player != b.player_1 && player != b.player_2 {
+                return Ok(Json(PerformMoveResponse::new(
+                    (false, "Invalid move: You are not playing on this board."),
+                    (-1, -1),
+                    (-1, -1),
+                    "".to_owned(),
+                    "".to_owned(),
+                    &Board::empty()
+                )));
+            }
+
+            // the player to move ran out of time before moving
+            if let Some(loser) = b.flagged_player(Utc::now().timestamp_millis()) {
+                let winner = b.opponent(&loser);
+                end.end_game(&mut b, &winner, EndReason::Timeout);
+
+                return Ok(Json(PerformMoveResponse::new(
+                    (true, ""),
+                    (-1, -1),
+                    (-1, -1),
+                    winner.clone(),
+                    b.last_player.clone(),
+                    &b.clone()
+                )));
+            }
+
+            // nobody moves while the computer is working out its reply, giving up stops it.
+            // a reply that got lost, such as when the server restarted, is worked out again
+            if col != -1 && b.get_next_player() == COMPUTER_STR {
+                if !engine.pending(&b.id) {
+                    engine.submit(&b);
+                }
+                return Ok(Json(PerformMoveResponse::new(
+                    (false, "Invalid move: The computer is still thinking."),
+                    (-1, -1),
+                    (-1, -1),
+                    "".to_owned(),
+                    "".to_owned(),
+                    &Board::empty()
+                )));
+            }
+
             // give up case
             if col == -1 {
-                let winner = b.last_player.clone();
-                db.delete_board(&b);
-                HistRepo::init().push_hist(&b, &winner);
-                UserRepo::init().add_score(&winner, b.difficulty.clone());
-                UserRepo::init().add_score(&b.opponent(&winner), -b.difficulty.clone()*10);
+                // a computer reply saved just before the cancel is part of the game that is given up
+                engine.cancel_board(&b.id);
+                if let Some(latest) = db.get_board_by_id(&b.id) {
+                    b = latest;
+                }
+                let winner = b.opponent(&player);
+                let loser = b.opponent(&winner);
+                hub.publish(db, &mut b, GameEvent::Resigned { player: loser });
+                end.end_game(&mut b, &winner, EndReason::Resigned);
 
                 return Ok(Json(PerformMoveResponse::new(
                     (true, ""),
@@ -68,9 +195,25 @@ pub fn perform_move(db: &State<BoardRepo>, move_req: Json<PerformMoveRequest>) -
                 )));
             }
 
+            // a player may only move on their turn
+            if player != b.get_next_player() {
+                return Ok(Json(PerformMoveResponse::new(
+                    (false, "Invalid move: It is not your turn."),
+                    (-1, -1),
+                    (-1, -1),
+                    "".to_owned(),
+                    "".to_owned(),
+                    &Board::empty()
+                )));
+            }
+
             if b.allows_move(&col) {
                 let next_player = b.get_next_player();
-                b.perform_move(col.clone(), next_player.clone());
+                b.record_move(col, next_player.clone(), None);
+                b.press_clock(&next_player, Utc::now().timestamp_millis());
+                b.last_activity_ms = Utc::now().timestamp_millis();
+                let (r, c) = (b.last_row, b.last_col);
+                hub.publish(db, &mut b, GameEvent::MoveMade { player: next_player.clone(), row: r, col: c });
             } else {
                 // case when move is invalid
                 return Ok(Json(PerformMoveResponse::new(
@@ -82,134 +225,72 @@ pub fn perform_move(db: &State<BoardRepo>, move_req: Json<PerformMoveRequest>) -
                     &Board::empty()
                 )));
             }
-            let human_move = (b.last_row.clone(), b.last_col.clone());
+            let human_move = (b.last_row, b.last_col);
 
             // case when human wins, draw, or lose
-            match b._has_winner() {
-                (true, winner) => {
+            if let (true, winner) = b._has_winner() {
 
-                    db.delete_board(&b);
-                    HistRepo::init().push_hist(&b, &winner);
-                    UserRepo::init().add_score(&winner, b.difficulty.clone());
-                    UserRepo::init().add_score(&b.opponent(&winner), -b.difficulty.clone());
-
-                    return Ok(Json(PerformMoveResponse::new(
-                        (true, ""),
-                        human_move.clone(),
-                        (-1, -1),
-                        winner.clone(),
-                        b.last_player.clone(),
-                        &b.clone()
-                    )));
-                },
-                (false, _) => ()
-            };
-            match b.is_draw() {
-                true => {
-
-                    db.delete_board(&b);
-                    HistRepo::init().push_hist(&b, &DRAW_STR.to_owned());
-
-                    return Ok(Json(PerformMoveResponse::new(
-                        (true, ""),
-                        human_move.clone(),
-                        (-1, -1),
-                        DRAW_STR.to_owned(),
-                        b.last_player.clone(),
-                        &b.clone()
-                    )));
-                },
-                false => (),
-            };
-            assert!(!b.is_terminal());
+                end.end_game(&mut b, &winner, EndReason::Connected);
 
-            // case when the opposite is computer
-            if b.get_next_player() == COMPUTER_STR {
-                let mut b_sim = b.clone();
-
-                // add a mutation possibility
-                let (a, bc, c) = (
-                    b_sim.difficulty.clone(),
-                    b_sim.difficulty.clone() - 2,
-                    b_sim.difficulty.clone() - 4
-                );
-                b_sim.difficulty = vec![
-                    a, a, a, a, a, a, a,
-                    bc, bc,
-                    c
-                ].choose(&mut rand::thread_rng()).unwrap().clone();
-                if b_sim.difficulty < 1 {
-                    b_sim.difficulty = 1;
-                }
+                return Ok(Json(PerformMoveResponse::new(
+                    (true, ""),
+                    human_move,
+                    (-1, -1),
+                    winner.clone(),
+                    b.last_player.clone(),
+                    &b.clone()
+                )));
+            }
+            if b.is_draw() {
 
-                let (_, best_move) = b_sim.alpha_beta(
-                    b.get_next_player(),
-                    i64::MIN,
-                    i64::MAX,
-                    b.difficulty
-                );
+                end.end_game(&mut b, &DRAW_STR.to_owned(), EndReason::Draw);
 
-                let next_player = b.get_next_player();
-                b.perform_move(best_move.clone(), next_player.clone());
-            } else {
-                b.last_row = -1;
-                b.last_col = -1;
+                return Ok(Json(PerformMoveResponse::new(
+                    (true, ""),
+                    human_move,
+                    (-1, -1),
+                    DRAW_STR.to_owned(),
+                    b.last_player.clone(),
+                    &b.clone()
+                )));
             }
-            let cmput_move = (b.last_row.clone(), b.last_col.clone());
-
-            // case when computer wins, draw, or lose
-            match b._has_winner() {
-                (true, winner) => {
-
-                    db.delete_board(&b);
-                    HistRepo::init().push_hist(&b, &winner);
-                    UserRepo::init().add_score(&winner, b.difficulty.clone());
-                    UserRepo::init().add_score(&b.opponent(&winner), -b.difficulty.clone());
-
-                    return Ok(Json(PerformMoveResponse::new(
-                        (true, ""),
-                        human_move.clone(),
-                        cmput_move.clone(),
-                        winner.clone(),
-                        b.last_player.clone(),
-                        &b.clone()
-                    )))
-                },
-                (false, _) => ()
-            };
-            match b.is_draw() {
-                true => {
-
-                    db.delete_board(&b);
-                    HistRepo::init().push_hist(&b, &DRAW_STR.to_owned());
-                    
-                    return Ok(Json(PerformMoveResponse::new(
-                        (true, ""),
-                        human_move.clone(),
-                        cmput_move.clone(),
-                        DRAW_STR.to_owned(),
+            assert!(!b.is_terminal());
+
+            // case when the opposite is computer, an engine worker replies and the reply follows
+            // on the event stream, the job can be polled as well
+            if b.get_next_player() == COMPUTER_STR {
+                let mut res = match db.update_board(&b) {
+                    true => PerformMoveResponse::new((true, ""), human_move, (-1, -1), "".to_owned(), b.last_player.clone(), &b),
+                    false => return Ok(Json(PerformMoveResponse::new(
+                        (false, "Database not connected."),
+                        human_move,
+                        (-1, -1),
+                        "".to_owned(),
                         b.last_player.clone(),
                         &b.clone()
-                    )))
-                },
-                false => (),
-            };
-            assert!(!b.is_terminal());
+                    ))),
+                };
+                res.job_id = engine.submit(&b);
+                return Ok(Json(res));
+            }
+            b.last_row = -1;
+            b.last_col = -1;
+            let cmput_move = (b.last_row, b.last_col);
 
-            // update the board into mongodb & send the computer move if there is one
+            // update the board into mongodb
             match db.update_board(&b) {
-                true => return Ok(Json(PerformMoveResponse::new(
+                true => Ok(Json(PerformMoveResponse::new(
                     (true, ""),
-                    human_move.clone(),
-                    cmput_move.clone(),
+                    human_move,
+                    cmput_move,
                     "".to_owned(),
                     b.last_player.clone(),
                     &b.clone()
                 ))),
-                false => return Ok(Json(PerformMoveResponse::new(
+                false => Ok(Json(PerformMoveResponse::new(
                     (false, "Database not connected."),
-                    human_move.clone(),
-                    cmput_move.clone(),
+                    human_move,
+                    cmput_move,
                     "".to_owned(),
                     b.last_player.clone(),
                     &b.clone()
@@ -218,7 +299,7 @@ pub fn perform_move(db: &State<BoardRepo>, move_req: Json<PerformMoveRequest>) -
         },
 
         // no matched board found in database
-        None => return Ok(Json(PerformMoveResponse::new(
+        None => Ok(Json(PerformMoveResponse::new(
             (false, "Board does not exist or database not connected."),
             (-1, -1),
             (-1, -1),
@@ -229,14 +310,45 @@ pub fn perform_move(db: &State<BoardRepo>, move_req: Json<PerformMoveRequest>) -
     }
 }
 
+// find a running board by its players and settings, showing only what spectators may see
 #[post("/board/info", data = "<board>")]
-pub fn get_board(db: &State<BoardRepo>, board: Json<Board>) -> Result<Json<GeneralBoardResponse>, Status> {
+pub fn get_board(db: &State<BoardRepo>, hub: &State<GameHub>, board: Json<Board>) -> Result<Json<BoardViewResponse>, Status> {
 
     match db.get_board(&board) {
 
-        Some(board) => Ok(Json(GeneralBoardResponse {
+        Some(board) => Ok(Json(BoardViewResponse {
             status: GeneralStatus::success(),
-            board
+            board: SpectatorView::new(&board, hub.spectators(&board.id))
+        })),
+
+        None => Ok(Json(BoardViewResponse {
+            status: GeneralStatus::failure("Board does not exist or database not connected."),
+            board: SpectatorView::empty()
+        })),
+    }
+}
+
+// get the whole board of a running game, including its event log, for one of its players
+#[post("/board/<id>/state", data = "<req>")]
+pub fn get_board_state(db: &State<BoardRepo>, users: &State<UserRepo>, id: String, req: Json<PlayerRequest>) -> Result<Json<GeneralBoardResponse>, Status> {
+
+    if login(users, &req.name, &req.pwd).is_none() {
+        return Ok(Json(GeneralBoardResponse {
+            status: GeneralStatus::failure("User password combination does not exist."),
+            board: Board::empty()
+        }));
+    }
+
+    match db.get_board_by_id(&id) {
+
+        Some(b) if req.name == b.player_1 || req.name == b.player_2 => Ok(Json(GeneralBoardResponse {
+            status: GeneralStatus::success(),
+            board: b
+        })),
+
+        Some(_) => Ok(Json(GeneralBoardResponse {
+            status: GeneralStatus::failure("You are not playing on this board."),
+            board: Board::empty()
         })),
 
         None => Ok(Json(GeneralBoardResponse {
@@ -246,17 +358,224 @@ pub fn get_board(db: &State<BoardRepo>, board: Json<Board>) -> Result<Json<Gener
     }
 }
 
+// what spectators may see of every running game
 #[get("/board/all")]
-pub fn get_all_boards(db: &State<BoardRepo>) -> Result<Json<GetAllBoardResponse>, Status> {
+pub fn get_all_boards(db: &State<BoardRepo>, hub: &State<GameHub>) -> Result<Json<GetAllBoardResponse>, Status> {
 
     match db.get_all_boards() {
-        Some(all_boards) => Ok(Json(GetAllBoardResponse {
+        Some(boards) => Ok(Json(GetAllBoardResponse {
             status: GeneralStatus::success(),
-            all_boards
+            all_boards: boards.iter()
+                .map(|b| SpectatorView::new(b, hub.spectators(&b.id)))
+                .collect()
         })),
         None => Ok(Json(GetAllBoardResponse {
             status: GeneralStatus::failure("Database not connected."),
             all_boards: vec![]
         })),
     }
-}
\ No newline at end of file
+}
+
+/*
+Get the time left of both players.
+A player who ran out of time while it was their turn loses here, so that the game
+also ends when the opponent stops moving altogether.
+ */
+#[get("/board/<id>/clock")]
+pub fn check_clock(db: &State<BoardRepo>, hist: &State<HistRepo>, users: &State<UserRepo>, hub: &State<GameHub>, engine: &State<EnginePool>, id: String) -> Result<Json<ClockResponse>, Status> {
+
+    let end = GameEnd { db, hist, users, hub, engine };
+
+    let mut b = match db.get_board_by_id(&id) {
+        Some(b) => b,
+        None => return Ok(Json(ClockResponse {
+            status: GeneralStatus::failure("Board does not exist or database not connected."),
+            clock_1_ms: -1,
+            clock_2_ms: -1,
+            winner: "".to_owned(),
+            reason: None,
+        })),
+    };
+
+    let now = Utc::now().timestamp_millis();
+    let winner = match b.flagged_player(now) {
+        Some(loser) => {
+            let winner = b.opponent(&loser);
+            end.end_game(&mut b, &winner, EndReason::Timeout);
+            winner
+        },
+        None => "".to_owned(),
+    };
+
+    Ok(Json(ClockResponse {
+        status: GeneralStatus::success(),
+        clock_1_ms: b.time_left(&b.player_1, now),
+        clock_2_ms: b.time_left(&b.player_2, now),
+        winner,
+        reason: b.end_reason.clone(),
+    }))
+}
+
+/*
+List the games being played right now so that anyone can pick one to watch.
+ */
+#[get("/board/live")]
+pub fn get_live_games(db: &State<BoardRepo>, hub: &State<GameHub>) -> Result<Json<GetLiveGamesResponse>, Status> {
+
+    match db.get_all_boards() {
+
+        Some(boards) => Ok(Json(GetLiveGamesResponse {
+            status: GeneralStatus::success(),
+            live_games: boards.iter()
+                .map(|b| LiveGame::new(b, hub.spectators(&b.id)))
+                .collect(),
+        })),
+
+        None => Ok(Json(GetLiveGamesResponse {
+            status: GeneralStatus::failure("Database not connected."),
+            live_games: vec![],
+        })),
+    }
+}
+
+// get the current position of a running game without anything only the players should see
+#[get("/board/<id>/spectate")]
+pub fn spectate_board(db: &State<BoardRepo>, hub: &State<GameHub>, id: String) -> Result<Json<SpectateResponse>, Status> {
+
+    match db.get_board_by_id(&id) {
+
+        Some(b) => Ok(Json(SpectateResponse {
+            status: GeneralStatus::success(),
+            view: SpectatorView::new(&b, hub.spectators(&b.id)),
+        })),
+
+        None => Ok(Json(SpectateResponse {
+            status: GeneralStatus::failure("Board does not exist or database not connected."),
+            view: SpectatorView::empty(),
+        })),
+    }
+}
+
+// apply a takeback action of a player to the board, giving the event to publish
+fn answer_takeback(b: &mut Board, player: &String, action: &TakebackAction) -> Result<GameEvent, &'static str> {
+
+    let opponent = b.opponent(player);
+    let requester = match action {
+        TakebackAction::Request => player.clone(),
+        TakebackAction::Accept | TakebackAction::Decline => opponent.clone(),
+    };
+
+    match action {
+        TakebackAction::Request if !b.takeback_request.is_empty() => return Err("A takeback was already requested."),
+        TakebackAction::Request if b.takeback_len(player) == 0 => return Err("You have no move to take back."),
+        TakebackAction::Request if opponent == COMPUTER_STR && b.takebacks >= b.takeback_limit() => return Err("No takebacks left in this game."),
+        TakebackAction::Request if opponent != COMPUTER_STR => {
+            b.takeback_request = player.clone();
+            return Ok(GameEvent::TakebackRequested { player: player.clone() });
+        },
+        TakebackAction::Request => b.takebacks += 1,
+        _ if b.takeback_request != opponent => return Err("There is no takeback to answer."),
+        TakebackAction::Decline => {
+            b.takeback_request = "".to_owned();
+            return Ok(GameEvent::TakebackDeclined { player: player.clone() });
+        },
+        TakebackAction::Accept => (),
+    };
+
+    let n = b.takeback_len(&requester);
+    b.take_back(n);
+    let now = Utc::now().timestamp_millis();
+    if b.turn_started_ms > 0 {
+        b.turn_started_ms = now;
+    }
+    b.last_activity_ms = now;
+    Ok(GameEvent::TakenBack { moves: n, next_player: b.get_next_player() })
+}
+
+/*
+Take back the last move of a player, together with every move made after it.
+Against the computer the takeback is granted right away as long as the limit of the game
+is not used up, against another player it is only done once they accept it.
+ */
+#[post("/board/<id>/takeback", data = "<req>")]
+pub fn takeback(db: &State<BoardRepo>, users: &State<UserRepo>, hub: &State<GameHub>, engine: &State<EnginePool>, id: String, req: Json<TakebackRequest>) -> Result<Json<GeneralBoardResponse>, Status> {
+
+    if login(users, &req.name, &req.pwd).is_none() {
+        return Ok(Json(GeneralBoardResponse {
+            status: GeneralStatus::failure("User password combination does not exist."),
+            board: Board::empty(),
+        }));
+    }
+
+    let mut b = match db.get_board_by_id(&id) {
+        Some(b) if req.name == b.player_1 || req.name == b.player_2 => b,
+        _ => return Ok(Json(GeneralBoardResponse {
+            status: GeneralStatus::failure("Board does not exist or you are not playing on it."),
+            board: Board::empty(),
+        })),
+    };
+
+    let event = match answer_takeback(&mut b, &req.name, &req.action) {
+        Ok(e) => e,
+        Err(msg) => return Ok(Json(GeneralBoardResponse {
+            status: GeneralStatus::failure(msg),
+            board: b,
+        })),
+    };
+    // a reply the computer is working out would answer a move that is gone now
+    if matches!(event, GameEvent::TakenBack { .. }) {
+        engine.cancel_board(&b.id);
+    }
+    hub.publish(db, &mut b, event);
+
+    match db.update_board(&b) {
+        true => Ok(Json(GeneralBoardResponse {
+            status: GeneralStatus::success(),
+            board: b,
+        })),
+        false => Ok(Json(GeneralBoardResponse {
+            status: GeneralStatus::failure("Database not connected."),
+            board: b,
+        })),
+    }
+}
+
+/*
+Suggest a move to the player to move, worked out by the engine workers like computer replies.
+Each player only gets a few hints per game and every hint taken makes winning the game worth less.
+ */
+#[post("/board/<id>/hint", data = "<req>")]
+pub fn get_hint(db: &State<BoardRepo>, users: &State<UserRepo>, engine: &State<EnginePool>, config: &State<AppConfig>, id: String, req: Json<HintRequest>) -> Result<Json<HintResponse>, Status> {
+
+    if login(users, &req.name, &req.pwd).is_none() {
+        return Ok(Json(HintResponse::failure("User password combination does not exist.")));
+    }
+
+    let mut b = match db.get_board_by_id(&id) {
+        Some(b) if req.name == b.get_next_player() => b,
+        Some(_) => return Ok(Json(HintResponse::failure("Hints are only given to the player to move."))),
+        None => return Ok(Json(HintResponse::failure("Board does not exist or database not connected."))),
+    };
+
+    if b.hints(&req.name) >= MAX_HINTS {
+        return Ok(Json(HintResponse::failure("No hints left in this game.")));
+    }
+
+    // the engine workers may be busy with a reply first, so wait for two searches
+    let (col, score, pv) = match engine.hint(&b, config.engine_timeout_ms * 2) {
+        Some(h) => h,
+        None => return Ok(Json(HintResponse::failure("The engine is busy, please try again."))),
+    };
+    b.take_hint(&req.name);
+
+    match db.update_board(&b) {
+        true => Ok(Json(HintResponse {
+            status: GeneralStatus::success(),
+            col,
+            score,
+            pv,
+            hints_left: MAX_HINTS - b.hints(&req.name),
+        })),
+        false => Ok(Json(HintResponse::failure("Database not connected."))),
+    }
+}
@@ -0,0 +1,63 @@
+use crate::{
+    api::user_api::login,
+    models::{
+        chat_model::*,
+        config_model::AppConfig,
+        general_model::GeneralStatus
+    },
+    repository::{board_repo::BoardRepo, hist_repo::HistRepo, user_repo::UserRepo},
+    services::{chat::post_chat, game_hub::GameHub}
+};
+
+use rocket::{
+    http::Status,
+    serde::json::Json,
+    State
+};
+
+#[post("/board/<id>/chat", data = "<req>")]
+pub fn send_chat(db: &State<BoardRepo>, users: &State<UserRepo>, hub: &State<GameHub>, config: &State<AppConfig>, id: String, req: Json<SendChatRequest>) -> Result<Json<SendChatResponse>, Status> {
+
+    if login(users, &req.name, &req.pwd).is_none() {
+        return Ok(Json(SendChatResponse {
+            status: GeneralStatus::failure("User password combination does not exist."),
+            message: None,
+        }));
+    }
+
+    match post_chat(db, hub, &id, &req.name, &req.text, config.spectator_chat) {
+
+        Ok(message) => Ok(Json(SendChatResponse {
+            status: GeneralStatus::success(),
+            message: Some(message),
+        })),
+
+        Err(msg) => Ok(Json(SendChatResponse {
+            status: GeneralStatus::failure(msg),
+            message: None,
+        })),
+    }
+}
+
+// get the chat of a game, finished games are looked up in history
+#[get("/board/<id>/chat")]
+pub fn get_chat(db: &State<BoardRepo>, hist: &State<HistRepo>, id: String) -> Result<Json<GetChatResponse>, Status> {
+
+    let chat = match db.get_board_by_id(&id) {
+        Some(b) => Some(b.chat()),
+        None => hist.get_hist_by_id(&id).map(|h| h.board.chat()),
+    };
+
+    match chat {
+
+        Some(chat) => Ok(Json(GetChatResponse {
+            status: GeneralStatus::success(),
+            chat,
+        })),
+
+        None => Ok(Json(GetChatResponse {
+            status: GeneralStatus::failure("Board does not exist or database not connected."),
+            chat: vec![],
+        })),
+    }
+}
@@ -0,0 +1,32 @@
+use crate::{
+    models::{
+        engine_model::*,
+        general_model::GeneralStatus
+    },
+    services::engine::EnginePool
+};
+
+use rocket::{
+    http::Status,
+    serde::json::Json,
+    State
+};
+
+/*
+Poll a computer reply that was deferred by /board/move. Finished jobs are only kept for a while,
+the reply is on the board and its event stream either way.
+ */
+#[get("/engine/job/<id>")]
+pub fn get_engine_job(engine: &State<EnginePool>, id: String) -> Result<Json<EngineJobResponse>, Status> {
+
+    match engine.job(&id) {
+        Some(job) => Ok(Json(EngineJobResponse {
+            status: GeneralStatus::success(),
+            job,
+        })),
+        None => Ok(Json(EngineJobResponse {
+            status: GeneralStatus::failure("Engine job does not exist or finished too long ago."),
+            job: EngineJob::empty(),
+        })),
+    }
+}
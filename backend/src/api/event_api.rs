@@ -0,0 +1,184 @@
+use crate::{
+    models::{
+        board_model::Board,
+        event_model::{GameEvent, GameEventEntry}
+    },
+    repository::{board_repo::BoardRepo, hist_repo::HistRepo},
+    services::{game_hub::GameHub, sessions::Sessions}
+};
+
+use rocket::{
+    http::Status,
+    request::{FromRequest, Outcome, Request},
+    response::stream::{Event, EventStream},
+    tokio::{select, sync::broadcast::error::RecvError, task::spawn_blocking},
+    Shutdown,
+    State
+};
+
+// the sequence number of the last event a reconnecting client has seen, 0 if none
+pub struct LastEventId(pub i64);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LastEventId {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+
+        let seq = match req.headers().get_one("Last-Event-ID") {
+            Some(v) => v.trim().parse().unwrap_or(0),
+            None => 0,
+        };
+        Outcome::Success(LastEventId(seq))
+    }
+}
+
+// the user the token in the query string was given to, None for spectators and unknown tokens
+pub struct Viewer(pub Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Viewer {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+
+        let name = match (req.query_value::<String>("token"), req.rocket().state::<Sessions>()) {
+            (Some(Ok(token)), Some(sessions)) => sessions.name_of(&token),
+            _ => None,
+        };
+        Outcome::Success(Viewer(name))
+    }
+}
+
+// events that are not part of the game log carry no id so that they do not move Last-Event-ID
+fn to_sse(entry: &GameEventEntry) -> Event {
+    let event = Event::json(entry).event(entry.event.name());
+    match entry.seq {
+        0 => event,
+        seq => event.id(seq.to_string()),
+    }
+}
+
+// find a board that is either being played or already moved into history
+fn find_board(db: &BoardRepo, hist: &HistRepo, id: &str) -> Option<(Board, bool)> {
+
+    match db.get_board_by_id(id) {
+        Some(b) => Some((b, false)),
+        None => match hist.get_hist_by_id(id) {
+            Some(h) => Some((h.board, true)),
+            None => None,
+        },
+    }
+}
+
+/*
+Stream the events of a board as server-sent events.
+Events the client missed since Last-Event-ID are replayed first, then live events follow
+until the game is over. A player passing the token they got when logging in lets the opponent
+know they have joined, everyone else is counted as a spectator and does not get to see private events.
+ */
+#[get("/board/<id>/events")]
+pub async fn game_events(db: &State<BoardRepo>, hist: &State<HistRepo>, hub: &State<GameHub>, id: String, viewer: Viewer, last_event: LastEventId, mut end: Shutdown) -> Option<EventStream![]> {
+
+    let player = viewer.0;
+
+    // subscribe before reading the log so that no event falls in between
+    let mut live = hub.subscribe(&id);
+    let (db, hist, hub) = (db.inner().clone(), hist.inner().clone(), hub.inner().clone());
+
+    let (repo, hist_repo, board_id) = (db.clone(), hist.clone(), id.clone());
+    let (board, finished) = spawn_blocking(move || find_board(&repo, &hist_repo, &board_id)).await.ok()??;
+
+    let spectating = match player {
+        Some(p) if p == board.player_1 || p == board.player_2 => {
+            if !finished {
+                let (repo, board_id, hub) = (db.clone(), id.clone(), hub.clone());
+                let _ = spawn_blocking(move || {
+                    hub.publish_by_id(&repo, &board_id, GameEvent::OpponentJoined { player: p })
+                }).await;
+            }
+            false
+        },
+        _ => true,
+    };
+
+    Some(EventStream! {
+        let _guard = match spectating && !finished {
+            true => Some(hub.watch(&id)),
+            false => None,
+        };
+
+        let mut last = last_event.0;
+        for entry in board.events {
+            if spectating && entry.event.is_private() {
+                continue;
+            }
+            if entry.seq > last {
+                last = entry.seq;
+                yield to_sse(&entry);
+            }
+        }
+
+        if !finished {
+            loop {
+                let missed = select! {
+                    msg = live.recv() => match msg {
+                        Ok(entry) => vec![entry],
+                        Err(RecvError::Closed) => break,
+                        // fell behind the channel, catch up from the stored log
+                        Err(RecvError::Lagged(_)) => {
+                            let (repo, hist_repo, board_id) = (db.clone(), hist.clone(), id.clone());
+                            match spawn_blocking(move || find_board(&repo, &hist_repo, &board_id)).await {
+                                Ok(Some((b, _))) => b.events,
+                                _ => break,
+                            }
+                        },
+                    },
+                    _ = &mut end => break,
+                };
+
+                for entry in missed {
+                    if spectating && entry.event.is_private() {
+                        continue;
+                    }
+                    if entry.seq == 0 {
+                        yield to_sse(&entry);
+                    } else if entry.seq > last {
+                        last = entry.seq;
+                        yield to_sse(&entry);
+                    }
+                }
+            }
+        }
+    })
+}
+
+/*
+Stream the events sent to a user while they wait in the lobby, such as their game being started.
+Only the user themselves may listen, so the token they got when logging in has to be passed.
+ */
+#[get("/lobby/<name>/events?<token>")]
+pub fn lobby_events(hub: &State<GameHub>, sessions: &State<Sessions>, name: String, token: Option<String>, mut end: Shutdown) -> Result<EventStream![], Status> {
+
+    let name = name.replace("_", " ");
+    match token.and_then(|t| sessions.name_of(&t)) {
+        Some(user) if user == name => (),
+        _ => return Err(Status::Unauthorized),
+    };
+
+    let mut live = hub.subscribe_user(&name);
+
+    Ok(EventStream! {
+        loop {
+            let entry = select! {
+                msg = live.recv() => match msg {
+                    Ok(entry) => entry,
+                    Err(RecvError::Closed) => break,
+                    Err(RecvError::Lagged(_)) => continue,
+                },
+                _ = &mut end => break,
+            };
+            yield Event::json(&entry.event).event(entry.event.name());
+        }
+    })
+}
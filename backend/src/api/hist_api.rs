@@ -1,9 +1,11 @@
 use crate::{
+    api::user_api::login,
     models::{
         board_model::*,
-        general_model::GeneralStatus
+        general_model::GeneralStatus,
+        notation_model::*
     },
-    repository::hist_repo::HistRepo,
+    repository::{hist_repo::HistRepo, user_repo::UserRepo},
 };
 
 use rocket::{
@@ -12,21 +14,25 @@ use rocket::{
     State
 };
 
+const COMPUTER_STR: &str = "*";
+
 #[get("/hist/get/<user>")]
 pub fn get_hist(db: &State<HistRepo>, user: String) -> Result<Json<GetHistResponse>, Status> {
 
     let mut hist: Vec<HistBoard> = vec![];
     let user = user.replace("_", " ");
 
-    match db.get_hist(&user, "player_1") {
-        Some(h) => hist.append(&mut h.clone()),
-        None => (),
-    };
+    if let Some(h) = db.get_hist(&user, "player_1") {
 
-    match db.get_hist(&user, "player_2") {
-        Some(h) => hist.append(&mut h.clone()),
-        None => (),
-    };
+        hist.append(&mut h.clone());
+
+    }
+
+    if let Some(h) = db.get_hist(&user, "player_2") {
+
+        hist.append(&mut h.clone());
+
+    }
 
     hist.sort_by(|h1, h2| h1.date.cmp(&h2.date));
     hist.dedup_by(|h1, h2| h1.date.eq(&h2.date));
@@ -35,4 +41,117 @@ pub fn get_hist(db: &State<HistRepo>, user: String) -> Result<Json<GetHistRespon
         status: GeneralStatus::success(),
         hist
     }))
-}
\ No newline at end of file
+}
+
+// get one finished game with its full move list
+#[get("/hist/<id>")]
+pub fn get_hist_game(db: &State<HistRepo>, id: String) -> Result<Json<GeneralHistResponse>, Status> {
+
+    match db.get_hist_by_id(&id) {
+
+        Some(hist) => Ok(Json(GeneralHistResponse {
+            status: GeneralStatus::success(),
+            hist,
+        })),
+
+        None => Ok(Json(GeneralHistResponse {
+            status: GeneralStatus::failure("Game does not exist or database not connected."),
+            hist: HistBoard::empty(),
+        })),
+    }
+}
+
+/*
+Write a finished game down in game notation, ranked below /hist/get/<user> which shares its shape.
+Games recorded before moves were kept cannot be written down and are answered with 422.
+ */
+#[get("/hist/<id>/export", rank = 2)]
+pub fn export_game(db: &State<HistRepo>, id: String) -> Result<Json<ExportGameResponse>, Status> {
+
+    match db.get_hist_by_id(&id) {
+
+        Some(hist) => match hist.start_board() {
+            Ok(_) => Ok(Json(ExportGameResponse {
+                status: GeneralStatus::success(),
+                notation: GameNotation::from_hist(&hist).to_text(),
+            })),
+            Err(_) => Err(Status::UnprocessableEntity),
+        },
+
+        None => Ok(Json(ExportGameResponse {
+            status: GeneralStatus::failure("Game does not exist or database not connected."),
+            notation: "".to_owned(),
+        })),
+    }
+}
+
+// every position of a finished game for stepping through it, ranked like export_game
+#[get("/hist/<id>/replay", rank = 2)]
+pub fn replay_game(db: &State<HistRepo>, id: String) -> Result<Json<ReplayResponse>, Status> {
+
+    let hist = match db.get_hist_by_id(&id) {
+        Some(h) => h,
+        None => return Ok(Json(ReplayResponse {
+            status: GeneralStatus::failure("Game does not exist or database not connected."),
+            hist: HistBoard::empty(),
+            frames: vec![],
+        })),
+    };
+
+    match hist.replay() {
+        Ok(frames) => Ok(Json(ReplayResponse {
+            status: GeneralStatus::success(),
+            hist,
+            frames,
+        })),
+        Err(msg) => Ok(Json(ReplayResponse {
+            status: GeneralStatus::failure(&msg),
+            hist,
+            frames: vec![],
+        })),
+    }
+}
+
+/*
+Add a game written in game notation to the history of the user importing it.
+The game is replayed move by move and refused if it breaks any rule, scores are left alone.
+Only games against the computer can be imported, nobody else can vouch for a game between two users.
+ */
+#[post("/hist/import", data = "<req>")]
+pub fn import_game(db: &State<HistRepo>, users: &State<UserRepo>, req: Json<ImportGameRequest>) -> Result<Json<GeneralHistResponse>, Status> {
+
+    if login(users, &req.name, &req.pwd).is_none() {
+        return Ok(Json(GeneralHistResponse {
+            status: GeneralStatus::failure("User password combination does not exist."),
+            hist: HistBoard::empty(),
+        }));
+    }
+
+    let hist = match GameNotation::parse(&req.notation).and_then(|n| n.to_hist()) {
+        Ok(h) => h,
+        Err(msg) => return Ok(Json(GeneralHistResponse {
+            status: GeneralStatus::failure(&msg),
+            hist: HistBoard::empty(),
+        })),
+    };
+
+    let (p1, p2) = (&hist.board.player_1, &hist.board.player_2);
+    let own_game = (req.name.eq(p1) && p2 == COMPUTER_STR) || (p1 == COMPUTER_STR && req.name.eq(p2));
+    if !own_game {
+        return Ok(Json(GeneralHistResponse {
+            status: GeneralStatus::failure("You can only import your own games against the computer."),
+            hist: HistBoard::empty(),
+        }));
+    }
+
+    match db.insert_hist(&hist) {
+        true => Ok(Json(GeneralHistResponse {
+            status: GeneralStatus::success(),
+            hist,
+        })),
+        false => Ok(Json(GeneralHistResponse {
+            status: GeneralStatus::failure("Database not connected."),
+            hist,
+        })),
+    }
+}
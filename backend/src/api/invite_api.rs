@@ -0,0 +1,188 @@
+use crate::{
+    api::{board_api::start_board, user_api::login},
+    models::{
+        board_model::*,
+        event_model::GameEvent,
+        general_model::GeneralStatus,
+        invite_model::*,
+        lobby_model::LobbyLoginRequest
+    },
+    repository::{board_repo::BoardRepo, invite_repo::InviteRepo, user_repo::UserRepo},
+    services::{game_hub::GameHub, sessions::Sessions}
+};
+
+use rocket::{
+    http::Status,
+    serde::json::Json,
+    State
+};
+
+#[post("/invite/send", data = "<req>")]
+pub fn send_invite(db: &State<InviteRepo>, users: &State<UserRepo>, hub: &State<GameHub>, req: Json<SendInviteRequest>) -> Result<Json<GeneralInviteResponse>, Status> {
+
+    if login(users, &req.name, &req.pwd).is_none() {
+        return Ok(Json(GeneralInviteResponse {
+            status: GeneralStatus::failure("User password combination does not exist."),
+            invite: Invitation::empty(),
+        }));
+    }
+
+    if req.to == req.name || users.get_user(&req.to).is_none() {
+        return Ok(Json(GeneralInviteResponse {
+            status: GeneralStatus::failure("The invited user does not exist."),
+            invite: Invitation::empty(),
+        }));
+    }
+
+    if !req.settings.is_valid() {
+        return Ok(Json(GeneralInviteResponse {
+            status: GeneralStatus::failure("Invalid game settings."),
+            invite: Invitation::empty(),
+        }));
+    }
+
+    let invite = Invitation::new(req.name.clone(), req.to.clone(), req.settings.clone());
+    match db.create_invite(&invite) {
+
+        true => {
+            hub.notify_user(&invite.to, GameEvent::InviteReceived {
+                invite_id: invite.id.clone(),
+                from: invite.from.clone(),
+            });
+            Ok(Json(GeneralInviteResponse {
+                status: GeneralStatus::success(),
+                invite,
+            }))
+        },
+
+        false => Ok(Json(GeneralInviteResponse {
+            status: GeneralStatus::failure("Database not connected."),
+            invite,
+        })),
+    }
+}
+
+// find a pending invitation addressed to the user, expiring it if it is too old
+fn pending_invite(db: &InviteRepo, id: &str, user: &str) -> Result<Invitation, &'static str> {
+
+    let invite = match db.get_invite(id) {
+        Some(i) => i,
+        None => return Err("Invitation does not exist."),
+    };
+
+    match invite.answerable_by(user) {
+        Ok(()) => Ok(invite),
+        Err(msg) => {
+            if invite.status == InviteStatus::Pending && invite.is_expired() {
+                db.set_status(&invite.id, InviteStatus::Pending, InviteStatus::Expired, "");
+            }
+            Err(msg)
+        },
+    }
+}
+
+/*
+Accept an invitation: the board is created the same way as /board/create with the
+inviting user playing first, and both players are told about it on their lobby event stream.
+ */
+#[post("/invite/<id>/accept", data = "<req>")]
+pub fn accept_invite(db: &State<InviteRepo>, boards: &State<BoardRepo>, users: &State<UserRepo>, hub: &State<GameHub>, id: String, req: Json<LobbyLoginRequest>) -> Result<Json<GeneralBoardResponse>, Status> {
+
+    if login(users, &req.name, &req.pwd).is_none() {
+        return Ok(Json(GeneralBoardResponse {
+            status: GeneralStatus::failure("User password combination does not exist."),
+            board: Board::empty(),
+        }));
+    }
+
+    let invite = match pending_invite(db, &id, &req.name) {
+        Ok(i) => i,
+        Err(msg) => return Ok(Json(GeneralBoardResponse {
+            status: GeneralStatus::failure(msg),
+            board: Board::empty(),
+        })),
+    };
+
+    // claim the invitation before starting the game, only one of two accepts at the same time gets it
+    if !db.set_status(&invite.id, InviteStatus::Pending, InviteStatus::Accepted, "") {
+        return Ok(Json(GeneralBoardResponse {
+            status: GeneralStatus::failure("Invitation was already answered."),
+            board: Board::empty(),
+        }));
+    }
+
+    match start_board(boards, hub, &invite.settings.new_board(&invite.from, &invite.to)) {
+
+        Ok(board) => {
+            db.set_status(&invite.id, InviteStatus::Accepted, InviteStatus::Accepted, &board.id);
+            Ok(Json(GeneralBoardResponse {
+                status: GeneralStatus::success(),
+                board,
+            }))
+        },
+
+        Err(board) => {
+            db.set_status(&invite.id, InviteStatus::Accepted, InviteStatus::Pending, "");
+            Ok(Json(GeneralBoardResponse {
+                status: GeneralStatus::failure("You already have a game with these settings or database not connected."),
+                board: *board,
+            }))
+        },
+    }
+}
+
+#[post("/invite/<id>/decline", data = "<req>")]
+pub fn decline_invite(db: &State<InviteRepo>, users: &State<UserRepo>, hub: &State<GameHub>, id: String, req: Json<LobbyLoginRequest>) -> Result<Json<GeneralStatus>, Status> {
+
+    if login(users, &req.name, &req.pwd).is_none() {
+        return Ok(Json(GeneralStatus::failure("User password combination does not exist.")));
+    }
+
+    let invite = match pending_invite(db, &id, &req.name) {
+        Ok(i) => i,
+        Err(msg) => return Ok(Json(GeneralStatus::failure(msg))),
+    };
+
+    match db.set_status(&invite.id, InviteStatus::Pending, InviteStatus::Declined, "") {
+        true => {
+            hub.notify_user(&invite.from, GameEvent::InviteDeclined {
+                invite_id: invite.id.clone(),
+                by: invite.to.clone(),
+            });
+            Ok(Json(GeneralStatus::success()))
+        },
+        false => Ok(Json(GeneralStatus::failure("Invitation was already answered or database not connected."))),
+    }
+}
+
+// list the invitations of a user, only the user themselves may see them so the login token has to be passed
+#[get("/invite/list/<user>?<token>")]
+pub fn get_invites(db: &State<InviteRepo>, sessions: &State<Sessions>, user: String, token: Option<String>) -> Result<Json<GetInvitesResponse>, Status> {
+
+    let user = user.replace("_", " ");
+    match token.and_then(|t| sessions.name_of(&t)) {
+        Some(name) if name == user => (),
+        _ => return Ok(Json(GetInvitesResponse {
+            status: GeneralStatus::failure("Please log in again."),
+            incoming: vec![],
+            outgoing: vec![],
+        })),
+    };
+
+    db.expire_invites();
+
+    let (incoming, outgoing) = match (db.get_invites(&user, "to"), db.get_invites(&user, "from")) {
+        (Some(i), Some(o)) => (i, o),
+        _ => return Ok(Json(GetInvitesResponse {
+            status: GeneralStatus::failure("Database not connected."),
+            incoming: vec![],
+            outgoing: vec![],
+        })),
+    };
+
+    Ok(Json(GetInvitesResponse {
+        status: GeneralStatus::success(),
+        incoming,
+        outgoing,
+    }))
+}
@@ -0,0 +1,194 @@
+use crate::{
+    api::{board_api::start_board, user_api::login},
+    models::{
+        board_model::*,
+        general_model::GeneralStatus,
+        lobby_model::*
+    },
+    repository::{board_repo::BoardRepo, user_repo::UserRepo},
+    services::{game_hub::GameHub, lobby::Lobby}
+};
+
+use rocket::{
+    http::Status,
+    serde::json::Json,
+    State
+};
+
+#[post("/lobby/challenge", data = "<req>")]
+pub fn post_challenge(users: &State<UserRepo>, lobby: &State<Lobby>, req: Json<PostChallengeRequest>) -> Result<Json<GeneralChallengeResponse>, Status> {
+
+    let user = match login(users, &req.name, &req.pwd) {
+        Some(u) => u,
+        None => return Ok(Json(GeneralChallengeResponse {
+            status: GeneralStatus::failure("User password combination does not exist."),
+            challenge: Challenge::empty(),
+        })),
+    };
+
+    if !req.settings.is_valid() {
+        return Ok(Json(GeneralChallengeResponse {
+            status: GeneralStatus::failure("Invalid game settings."),
+            challenge: Challenge::empty(),
+        }));
+    }
+
+    let challenge = Challenge::new(user.name.clone(), user.score, req.settings.clone());
+    lobby.post(challenge.clone());
+
+    Ok(Json(GeneralChallengeResponse {
+        status: GeneralStatus::success(),
+        challenge,
+    }))
+}
+
+#[get("/lobby/challenges")]
+pub fn get_all_challenges(lobby: &State<Lobby>) -> Result<Json<GetAllChallengeResponse>, Status> {
+
+    Ok(Json(GetAllChallengeResponse {
+        status: GeneralStatus::success(),
+        all_challenges: lobby.open_challenges(),
+    }))
+}
+
+#[post("/lobby/challenge/<id>/cancel", data = "<req>")]
+pub fn cancel_challenge(users: &State<UserRepo>, lobby: &State<Lobby>, id: String, req: Json<LobbyLoginRequest>) -> Result<Json<GeneralStatus>, Status> {
+
+    if login(users, &req.name, &req.pwd).is_none() {
+        return Ok(Json(GeneralStatus::failure("User password combination does not exist.")));
+    }
+
+    match lobby.cancel(&id, &req.name) {
+        true => Ok(Json(GeneralStatus::success())),
+        false => Ok(Json(GeneralStatus::failure("Challenge does not exist."))),
+    }
+}
+
+/*
+Accept an open challenge: the creator plays first, the board is created and both players
+are told about it on their lobby event stream.
+ */
+#[post("/lobby/challenge/<id>/accept", data = "<req>")]
+pub fn accept_challenge(db: &State<BoardRepo>, users: &State<UserRepo>, hub: &State<GameHub>, lobby: &State<Lobby>, id: String, req: Json<LobbyLoginRequest>) -> Result<Json<GeneralBoardResponse>, Status> {
+
+    if login(users, &req.name, &req.pwd).is_none() {
+        return Ok(Json(GeneralBoardResponse {
+            status: GeneralStatus::failure("User password combination does not exist."),
+            board: Board::empty(),
+        }));
+    }
+
+    let challenge = match lobby.take(&id) {
+        Some(c) => c,
+        None => return Ok(Json(GeneralBoardResponse {
+            status: GeneralStatus::failure("Challenge does not exist or was already accepted."),
+            board: Board::empty(),
+        })),
+    };
+
+    if challenge.creator == req.name {
+        lobby.post(challenge);
+        return Ok(Json(GeneralBoardResponse {
+            status: GeneralStatus::failure("You cannot accept your own challenge."),
+            board: Board::empty(),
+        }));
+    }
+
+    match start_board(db, hub, &challenge.settings.new_board(&challenge.creator, &req.name)) {
+
+        Ok(board) => {
+            lobby.clear_player(&board.player_1);
+            lobby.clear_player(&board.player_2);
+            Ok(Json(GeneralBoardResponse {
+                status: GeneralStatus::success(),
+                board,
+            }))
+        },
+
+        Err(board) => {
+            lobby.post(challenge);
+            Ok(Json(GeneralBoardResponse {
+                status: GeneralStatus::failure("You already have a game with these settings or database not connected."),
+                board: *board,
+            }))
+        },
+    }
+}
+
+/*
+Join the auto-match queue. When a waiting player with the same settings is within
+the rating range the game starts right away, otherwise the player is told on their
+lobby event stream once somebody matches them.
+ */
+#[post("/lobby/queue", data = "<req>")]
+pub fn join_queue(db: &State<BoardRepo>, users: &State<UserRepo>, hub: &State<GameHub>, lobby: &State<Lobby>, req: Json<JoinQueueRequest>) -> Result<Json<JoinQueueResponse>, Status> {
+
+    let user = match login(users, &req.name, &req.pwd) {
+        Some(u) => u,
+        None => return Ok(Json(JoinQueueResponse {
+            status: GeneralStatus::failure("User password combination does not exist."),
+            matched: false,
+            board: Board::empty(),
+        })),
+    };
+
+    if !req.settings.is_valid() || req.rating_range < 0 {
+        return Ok(Json(JoinQueueResponse {
+            status: GeneralStatus::failure("Invalid game settings."),
+            matched: false,
+            board: Board::empty(),
+        }));
+    }
+
+    let entry = QueueEntry {
+        name: user.name.clone(),
+        rating: user.score,
+        rating_range: req.rating_range,
+        settings: req.settings.clone(),
+    };
+
+    // the player who waited longer plays first
+    let opponent = match lobby.enqueue(entry.clone()) {
+        Some(o) => o,
+        None => return Ok(Json(JoinQueueResponse {
+            status: GeneralStatus::success(),
+            matched: false,
+            board: Board::empty(),
+        })),
+    };
+
+    match start_board(db, hub, &entry.settings.new_board(&opponent.name, &entry.name)) {
+
+        Ok(board) => {
+            lobby.clear_player(&board.player_1);
+            lobby.clear_player(&board.player_2);
+            Ok(Json(JoinQueueResponse {
+                status: GeneralStatus::success(),
+                matched: true,
+                board,
+            }))
+        },
+
+        Err(board) => {
+            lobby.requeue(opponent);
+            Ok(Json(JoinQueueResponse {
+                status: GeneralStatus::failure("You already have a game with these settings or database not connected."),
+                matched: false,
+                board: *board,
+            }))
+        },
+    }
+}
+
+#[post("/lobby/queue/leave", data = "<req>")]
+pub fn leave_queue(users: &State<UserRepo>, lobby: &State<Lobby>, req: Json<LobbyLoginRequest>) -> Result<Json<GeneralStatus>, Status> {
+
+    if login(users, &req.name, &req.pwd).is_none() {
+        return Ok(Json(GeneralStatus::failure("User password combination does not exist.")));
+    }
+
+    match lobby.leave_queue(&req.name) {
+        true => Ok(Json(GeneralStatus::success())),
+        false => Ok(Json(GeneralStatus::failure("You are not in the queue."))),
+    }
+}
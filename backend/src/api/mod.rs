@@ -1,3 +1,9 @@
 pub mod user_api;
 pub mod board_api;
 pub mod hist_api;
+pub mod event_api;
+pub mod lobby_api;
+pub mod invite_api;
+pub mod chat_api;
+pub mod analysis_api;
+pub mod engine_api;
@@ -5,7 +5,8 @@ use crate::{
         user_model::*,
         general_model::GeneralStatus
     },
-    repository::user_repo::UserRepo
+    repository::user_repo::UserRepo,
+    services::sessions::Sessions
 };
 
 use rocket::{
@@ -17,6 +18,16 @@ use rocket::{
 extern crate argon2;
 
 
+// check the password of a player and get their record, None if it does not match
+pub fn login(users: &UserRepo, name: &str, pwd: &String) -> Option<User> {
+
+    match users.verify_pwd(name, pwd) {
+        true => users.get_user(name),
+        false => None,
+    }
+}
+
+
 #[options("/<_p..>")]
 pub fn placeholder(_p: PathBuf) -> Result<(), ()> {
     Ok(())
@@ -42,12 +53,18 @@ pub fn create_user(db: &State<UserRepo>, new_user: Json<User>) -> Result<Json<Ge
 }
 
 
+// check the password of a user, handing out a token for the websocket and event streams if it matches
 #[post("/user/verify", data = "<user>")]
-pub fn verify_pwd(db: &State<UserRepo>, user: Json<User>) -> Result<Json<PwdVerifyResponse>, Status> {
+pub fn verify_pwd(db: &State<UserRepo>, sessions: &State<Sessions>, user: Json<User>) -> Result<Json<PwdVerifyResponse>, Status> {
 
+    let exists = db.verify_pwd(&user.name, &user.pwd);
     Ok(Json(PwdVerifyResponse {
         status: GeneralStatus::success(),
-        exists: db.verify_pwd(&user.name, &user.pwd),
+        exists,
+        token: match exists {
+            true => sessions.issue(&user.name),
+            false => "".to_owned(),
+        },
     }))
 }
 
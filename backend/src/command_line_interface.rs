@@ -1,3 +1,26 @@
+use std::{fs, path::Path};
+
+use chrono::prelude::*;
+use clap::{Args, Parser, Subcommand};
+use mongodb::bson::oid::ObjectId;
+use rocket::serde::json::to_string;
+
+use crate::{
+    network_client::{self, Client},
+    terminal_ui,
+    models::{
+        board_model::{Board, EndReason, MAX_SIZE, MIN_SIZE},
+        notation_model::GameNotation,
+        user_model::User,
+    },
+    repository::{board_repo::BoardRepo, hist_repo::HistRepo, user_repo::UserRepo},
+    services::{
+        analysis::{analyze, solve},
+        bench::{bench, perft},
+        selfplay::{run_match, EngineSpec},
+    },
+};
+
 const LINE: &str = "-----------------------------------------------------";
 
 fn log(str: &str) {
@@ -9,4 +32,525 @@ pub fn welcome() {
     log("ECE 421 Project 3 - Connect4 with TOOT and OTTO");
     log("Jianxi Wang, Yihe Wang, John Yu");
     println!("{}", LINE);
-}
\ No newline at end of file
+}
+
+// a game mode such as TOOT, false is a T and true an O
+#[derive(Debug, Clone)]
+pub struct Mode(pub Vec<bool>);
+
+// read a game mode such as TOOT, it has to be symmetric like the ones the web app offers
+pub fn parse_mode(input: &str) -> Result<Mode, String> {
+
+    let chars: Vec<char> = input.trim().chars().collect();
+    if chars.len() != 4 || !chars.iter().all(|c| *c == 'T' || *c == 'O') {
+        return Err(format!("Mode '{}' must be four characters T or O.", input));
+    }
+    if chars[0] != chars[3] || chars[1] != chars[2] {
+        return Err(format!("Mode '{}' must be symmetric.", input));
+    }
+    Ok(Mode(chars.iter().map(|c| *c == 'O').collect()))
+}
+
+// read a board size such as 7x6
+pub fn parse_size(input: &str) -> Result<(i64, i64), String> {
+
+    match input.trim().split_once('x') {
+        Some((w, h)) => match (w.parse::<i64>(), h.parse::<i64>()) {
+            (Ok(w), Ok(h)) if Board::valid_size(w, h) => Ok((w, h)),
+            _ => Err(format!("Size '{}' must be between {1}x{1} and {2}x{2}.", input, MIN_SIZE, MAX_SIZE)),
+        },
+        None => Err(format!("Size '{}' should look like 7x6.", input)),
+    }
+}
+
+#[derive(Parser)]
+#[command(name = "backend", about = "Connect4 with TOOT and OTTO: the game server and tools around it.")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,  // The server is started when left out.
+}
+
+// the board a command works on
+#[derive(Args, Clone)]
+pub struct BoardArgs {
+    #[arg(long, default_value = "7x6", value_parser = parse_size)]
+    pub size: (i64, i64),
+    #[arg(long, default_value = "TTTT", value_parser = parse_mode)]
+    pub mode: Mode,
+    #[arg(long)]
+    pub position: Option<String>,  // A position string, the empty board of the size and mode by default.
+}
+
+impl BoardArgs {
+
+    pub fn position(&self) -> String {
+        match &self.position {
+            Some(p) => p.clone(),
+            None => Board::new(self.size.0, self.size.1, "x".to_owned(), "o".to_owned(), self.mode.0.clone(), 1).to_position_string(),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Start the web server, the same as giving no command.
+    Serve,
+    /// Play a game in the terminal, "*" plays a player by the computer.
+    #[command(alias = "debug")]
+    Play {
+        #[arg(long, default_value = "7x6", value_parser = parse_size)]
+        size: (i64, i64),
+        #[arg(long, default_value = "TTTT", value_parser = parse_mode)]
+        mode: Mode,
+        #[arg(long, default_value_t = 5)]
+        difficulty: i64,  // How many moves the computer looks ahead.
+        #[arg(long, default_value = "player 1")]
+        player1: String,
+        #[arg(long, default_value = "*")]
+        player2: String,
+        #[arg(long)]
+        resume: bool,  // Continue the game between the players from the database.
+        #[arg(long, conflicts_with = "resume")]
+        file: Option<String>,  // A notation file to continue the game from if it exists, the game is saved to it after every move.
+        #[arg(long)]
+        tui: bool,  // Play full screen instead of typing column numbers.
+    },
+    /// Search a position and tell who wins with the best play.
+    Solve {
+        #[command(flatten)]
+        board: BoardArgs,
+        #[arg(long, default_value_t = 8)]
+        depth: i64,
+        #[arg(long, default_value_t = 1)]
+        threads: usize,
+    },
+    /// Judge every move of a finished game, from history or from a notation file.
+    Analyze {
+        id: Option<String>,
+        #[arg(long)]
+        file: Option<String>,
+        #[arg(long)]
+        save: bool,  // Store the analysis with the game in history.
+    },
+    /// Play engines against each other, an engine is level:<1-5>, ab:<depth> or mcts:<playouts>.
+    Selfplay {
+        #[arg(num_args = 2, required = true)]
+        engines: Vec<String>,
+        #[arg(long, default_value_t = 10)]
+        games: i64,
+        #[arg(long, default_value = "7x6", value_parser = parse_size, value_delimiter = ',')]
+        size: Vec<(i64, i64)>,
+        #[arg(long, default_value = "TTTT", value_parser = parse_mode, value_delimiter = ',')]
+        mode: Vec<Mode>,
+    },
+    /// Count the positions a number of moves ahead, as json.
+    Perft {
+        depth: i64,
+        #[command(flatten)]
+        board: BoardArgs,
+    },
+    /// Time the engine on a fixed set of positions, as json.
+    Bench {
+        #[arg(long)]
+        depth: Option<i64>,
+    },
+    /// Add a finished game from a notation file into history.
+    Import {
+        file: String,
+    },
+    /// Write a game from history in notation.
+    Export {
+        id: String,
+        #[arg(long)]
+        out: Option<String>,  // A file to write to instead of the terminal.
+    },
+    /// Manage user accounts.
+    User {
+        #[command(subcommand)]
+        command: UserCommand,
+    },
+    /// Play or watch games of a running server.
+    Client {
+        #[arg(long, default_value = "http://127.0.0.1:8000")]
+        server: String,
+        #[arg(long, default_value_t = 8001)]
+        ws_port: u16,  // Port of the websocket server, ws_port in Rocket.toml.
+        #[command(subcommand)]
+        command: ClientCommand,
+    },
+    /// Bring the boards and games stored by older versions up to the current format.
+    Migrate {
+        #[arg(long)]
+        analyze: bool,  // Also analyse finished games that have moves but no analysis.
+    },
+}
+
+#[derive(Subcommand)]
+pub enum UserCommand {
+    Create {
+        name: String,
+        pwd: String,
+    },
+    Info {
+        name: String,
+    },
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum ClientCommand {
+    /// List the games being played.
+    Games,
+    /// Start a game on the server and play it, "*" plays against the computer.
+    New {
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        pwd: String,
+        #[arg(long, default_value = "*")]
+        opponent: String,
+        #[arg(long, default_value_t = 5)]
+        difficulty: i64,
+        #[command(flatten)]
+        board: BoardArgs,
+    },
+    /// Play your moves in a running game.
+    Play {
+        id: String,
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        pwd: String,
+    },
+    /// Follow a running game as a spectator.
+    Watch {
+        id: String,
+    },
+}
+
+/*
+Run a command other than serve. The commands that need games or users go to the database,
+the others only use the engine.
+ */
+pub fn run(command: Command) -> Result<(), String> {
+    match command {
+        Command::Serve => Ok(()),
+        Command::Play { size, mode, difficulty, player1, player2, resume, file, tui } => play(size, mode, difficulty, (player1, player2), resume, file, tui),
+        Command::Solve { board, depth, threads } => solve_command(&board.position(), depth, threads),
+        Command::Analyze { id, file, save } => analyze_command(id, file, save),
+        Command::Selfplay { engines, games, size, mode } => selfplay(&engines, games, size, mode),
+        Command::Perft { depth, board } => {
+            let report = perft(&board.position(), depth)?;
+            println!("{}", to_string(&report).unwrap());
+            Ok(())
+        },
+        Command::Bench { depth } => {
+            println!("{}", to_string(&bench(depth)).unwrap());
+            Ok(())
+        },
+        Command::Import { file } => import(&file),
+        Command::Export { id, out } => export(&id, out),
+        Command::User { command } => user(command),
+        Command::Client { server, ws_port, command } => client(&Client::new(&server, ws_port)?, command),
+        Command::Migrate { analyze } => migrate(analyze),
+    }
+}
+
+/*
+Play a game in the terminal, human players type their moves.
+A game kept in a notation file goes on from there and needs no database.
+Both computers are called "" and "*" on the board so that they have different names.
+ */
+fn play(size: (i64, i64), mode: Mode, difficulty: i64, players: (String, String), resume: bool, file: Option<String>, tui: bool) -> Result<(), String> {
+
+    let (player1, player2) = players;
+    let player_1 = if player1 == "*" { "".to_owned() } else { player1 };
+    if player_1 == player2 || player_1 == "**" || player2 == "**" || player2.is_empty() {
+        return Err("The players need different names, and ** is not a name.".to_owned());
+    }
+    if difficulty < 1 {
+        return Err("The difficulty must be at least 1.".to_owned());
+    }
+    if file.is_some() && player_1.is_empty() && player2 == "*" {
+        return Err("Games between two computers are not saved.".to_owned());
+    }
+
+    welcome();
+    let new_board = Board::new(size.0, size.1, player_1.clone(), player2.clone(), mode.0, difficulty);
+    let mut game_board = match &file {
+        Some(f) if Path::new(f).exists() => {
+            let b = load_game(f)?;
+            println!("Continuing the game from {}.", f);
+            b
+        },
+        _ if resume => BoardRepo::init().get_board(&new_board).unwrap_or(new_board),
+        _ => new_board,
+    };
+
+    // a game that cannot be saved is still played on, the last save that worked stays in the file
+    let mut save = |b: &Board| if let Some(f) = &file { match save_game(f, b) {
+        Ok(()) => (),
+        Err(msg) => println!("{}", msg),
+    } };
+    let winner: String = match tui {
+        true => match terminal_ui::run(&mut game_board, &mut save)? {
+            Some(w) => w,
+            None => return Ok(()),
+        },
+        false => game_board.host_game(&mut save),
+    };
+    let (player_1, player2) = (game_board.player_1.clone(), game_board.player_2.clone());
+    if player_1.is_empty() && player2 == "*" {
+        if winner.is_empty() {
+            println!("Computer 1 wins -- Congratulations!");
+        } else {
+            println!("Computer 2 wins -- Congratulations!");
+        }
+    } else {
+        game_board.print_congrats();
+    }
+    Ok(())
+}
+
+// the computer playing first is called "" in the terminal, but "*" like on the server in notation
+fn notation_name(player: &str) -> String {
+    match player.is_empty() {
+        true => "*".to_owned(),
+        false => player.to_owned(),
+    }
+}
+
+// read a game that is not over yet from a notation file
+fn load_game(file: &String) -> Result<Board, String> {
+
+    let text = fs::read_to_string(file).map_err(|e| format!("Cannot read {}: {}", file, e))?;
+    let mut notation = GameNotation::parse(&text)?;
+    if notation.player_1 == "*" {
+        notation.player_1 = "".to_owned();
+    }
+    notation.resume().map_err(|e| format!("Cannot continue the game in {}: {}", file, e))
+}
+
+// write the game down in notation, with its result once it is over
+fn save_game(file: &String, board: &Board) -> Result<(), String> {
+
+    let (connected, winner) = board._has_winner();
+    let (winner, reason) = if connected {
+        (notation_name(&winner), Some(EndReason::Connected))
+    } else if board.is_draw() {
+        ("^".to_owned(), Some(EndReason::Draw))
+    } else {
+        ("".to_owned(), None)
+    };
+    let mut notation = GameNotation::from_board(board, winner, reason);
+    notation.player_1 = notation_name(&board.player_1);
+    fs::write(file, notation.to_text()).map_err(|e| format!("Cannot write {}: {}", file, e))
+}
+
+fn solve_command(position: &str, depth: i64, threads: usize) -> Result<(), String> {
+
+    if depth < 1 {
+        return Err("The depth must be at least 1.".to_owned());
+    }
+    let res = solve(position, depth, threads)?;
+    let line: Vec<String> = res.search.pv.iter().map(|m| m.to_string()).collect();
+
+    println!("{}", res.position);
+    println!("{} to move: {:?}", res.side_to_move, res.outcome);
+    println!("best moves: {:?}, score {} (positive when x is winning)", res.best_moves, res.search.score);
+    println!("line: {}", line.join(" "));
+    println!("{} positions to depth {}", res.search.nodes, res.search.depth);
+    Ok(())
+}
+
+fn analyze_command(id: Option<String>, file: Option<String>, save: bool) -> Result<(), String> {
+
+    if save && id.is_none() {
+        return Err("Only games in history can keep their analysis.".to_owned());
+    }
+    let hist = match (&id, &file) {
+        (Some(id), None) => match HistRepo::init().get_hist_by_id(id) {
+            Some(h) => h,
+            None => return Err("Game does not exist in history or database not connected.".to_owned()),
+        },
+        (None, Some(file)) => {
+            let text = fs::read_to_string(file).map_err(|e| format!("Cannot read {}: {}", file, e))?;
+            GameNotation::parse(&text)?.to_hist()?
+        },
+        _ => return Err("Give either the id of a game in history or --file.".to_owned()),
+    };
+
+    let analysis = analyze(&hist)?;
+    for a in &analysis {
+        println!("{:>3}. {} plays {}: {:?}, best {} ({} against {})", a.ply, a.player, a.col, a.verdict, a.best_col, a.score, a.best_score);
+    }
+    match id {
+        Some(id) if save => match HistRepo::init().set_analysis(&id, &analysis) {
+            true => println!("Analysis saved."),
+            false => return Err("Database not connected.".to_owned()),
+        },
+        _ => (),
+    };
+    Ok(())
+}
+
+/*
+Play two engines against each other and report how they did.
+ */
+fn selfplay(engines: &[String], games: i64, sizes: Vec<(i64, i64)>, modes: Vec<Mode>) -> Result<(), String> {
+
+    let engines: [EngineSpec; 2] = [EngineSpec::parse(&engines[0])?, EngineSpec::parse(&engines[1])?];
+    if games < 1 {
+        return Err("Play at least one game.".to_owned());
+    }
+    let modes: Vec<Vec<bool>> = modes.into_iter().map(|m| m.0).collect();
+
+    welcome();
+    log(&format!("{} against {}", engines[0].name(), engines[1].name()));
+    println!("{}", LINE);
+    let stats = run_match(&engines, games, &sizes, &modes);
+    let (elo, margin) = stats.elo();
+
+    println!("{}", LINE);
+    log(&format!("{} won {}, drew {}, lost {} of {} games", engines[0].name(), stats.wins, stats.draws, stats.losses, stats.games()));
+    log(&format!("Elo difference: {:+.1} +/- {:.1} (95%)", elo, margin));
+    for (e, engine) in engines.iter().enumerate() {
+        log(&format!("{} took {:.1} ms per move", engine.name(), stats.avg_move_ms(e)));
+    }
+    println!("{}", LINE);
+    Ok(())
+}
+
+fn import(file: &String) -> Result<(), String> {
+
+    let text = fs::read_to_string(file).map_err(|e| format!("Cannot read {}: {}", file, e))?;
+    let hist = GameNotation::parse(&text)?.to_hist()?;
+    match HistRepo::init().insert_hist(&hist) {
+        true => {
+            println!("Imported game {}.", hist.board.id);
+            Ok(())
+        },
+        false => Err("Database not connected.".to_owned()),
+    }
+}
+
+fn export(id: &str, out: Option<String>) -> Result<(), String> {
+
+    let hist = match HistRepo::init().get_hist_by_id(id) {
+        Some(h) => h,
+        None => return Err("Game does not exist in history or database not connected.".to_owned()),
+    };
+    let text = GameNotation::from_hist(&hist).to_text();
+    match out {
+        Some(file) => fs::write(&file, text).map_err(|e| format!("Cannot write {}: {}", file, e)),
+        None => {
+            print!("{}", text);
+            Ok(())
+        },
+    }
+}
+
+fn user(command: UserCommand) -> Result<(), String> {
+
+    let db = UserRepo::init();
+    match command {
+        UserCommand::Create { name, pwd } => match db.create_user(User::new(name.clone(), pwd)) {
+            true => println!("Created {}.", name),
+            false => return Err("User already exists or database not connected.".to_owned()),
+        },
+        UserCommand::Info { name } => match db.get_user(&name) {
+            Some(u) => println!("{}: {} points", u.name, u.score),
+            None => return Err("User does not exist or database not connected.".to_owned()),
+        },
+        UserCommand::List => {
+            let mut users = db.get_all_users().ok_or("Database not connected.")?;
+            users.sort_by_key(|u| -u.score);
+            for u in users {
+                println!("{}: {} points", u.name, u.score);
+            }
+        },
+    };
+    Ok(())
+}
+
+fn client(client: &Client, command: ClientCommand) -> Result<(), String> {
+
+    match command {
+        ClientCommand::Games => {
+            for g in client.live_games()? {
+                let mode: String = g.mode.iter().map(|o| if *o { 'O' } else { 'T' }).collect();
+                println!("{}: {} vs {}, {}x{} {}, {} moves, {} watching", g.id, g.player_1, g.player_2, g.width, g.height, mode, g.move_count, g.spectators);
+            }
+            Ok(())
+        },
+        ClientCommand::New { name, pwd, opponent, difficulty, board } => {
+            client.login(&name, &pwd)?;
+            let new_board = Board::new(board.size.0, board.size.1, name.clone(), opponent, board.mode.0.clone(), difficulty);
+            let b = client.create_board(&new_board, board.position.clone().unwrap_or_default())?;
+            println!("Started game {}.", b.id);
+            network_client::play(client, &b.id, &name, &pwd)
+        },
+        ClientCommand::Play { id, name, pwd } => network_client::play(client, &id, &name, &pwd),
+        ClientCommand::Watch { id } => network_client::watch(client, &id),
+    }
+}
+
+/*
+Read every stored board and finished game and write it back, so that fields added since
+it was stored are filled in with their defaults. Records are written back under the key mongodb
+gave them, since the oldest ones have no id yet and get one here.
+ */
+fn migrate(with_analysis: bool) -> Result<(), String> {
+
+    let now = Utc::now().timestamp_millis();
+    let boards = BoardRepo::init();
+    let active = boards.get_all_boards_by_key().ok_or("Database not connected.")?;
+    let mut migrated = 0;
+    for (key, mut b) in active.clone() {
+        if b.id.is_empty() {
+            b.id = ObjectId::new().to_hex();
+        }
+        // the reaper would otherwise see the board as idle since 1970
+        if b.last_activity_ms == 0 {
+            b.last_activity_ms = now;
+        }
+        if boards.replace_board_by_key(&key, &b) {
+            migrated += 1;
+        }
+    }
+    println!("Migrated {} of {} active boards.", migrated, active.len());
+
+    let hists = HistRepo::init();
+    let finished = hists.get_all_hist_by_key().ok_or("Database not connected.")?;
+    let (mut migrated, mut analysed) = (0, 0);
+    for (key, mut h) in finished.clone() {
+        if h.board.id.is_empty() {
+            h.board.id = ObjectId::new().to_hex();
+        }
+        if with_analysis && h.analysis.is_empty() && !h.board.moves.is_empty() {
+            match analyze(&h) {
+                Ok(a) => {
+                    h.analysis = a;
+                    analysed += 1;
+                },
+                Err(msg) => println!("Analysis of game {} failed: {}", h.board.id, msg),
+            };
+        }
+        if hists.replace_hist(&key, &h) {
+            migrated += 1;
+        }
+    }
+    println!("Migrated {} of {} finished games, analysed {}.", migrated, finished.len(), analysed);
+    Ok(())
+}
+
+// leave the program once a command is done, telling what went wrong if it failed
+pub fn finish(result: Result<(), String>) -> ! {
+    match result {
+        Ok(()) => std::process::exit(0),
+        Err(msg) => {
+            println!("{}", msg);
+            std::process::exit(1);
+        },
+    }
+}
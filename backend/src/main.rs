@@ -1,25 +1,32 @@
 mod api;
 mod command_line_interface;
 mod models;
+mod network_client;
 mod repository;
+mod services;
+mod terminal_ui;
 
 #[macro_use]
 extern crate rocket;
 
-use std::env;
-use std::io;
-use std::io::Write;
-
 use api::board_api::*;
-use api::hist_api::get_hist;
+use api::event_api::*;
+use api::lobby_api::*;
+use api::invite_api::*;
+use api::chat_api::*;
+use api::analysis_api::analyze_position;
+use api::engine_api::get_engine_job;
+use api::hist_api::{get_hist, get_hist_game, export_game, replay_game, import_game};
 use api::user_api::*;
-use models::board_model::Board;
+use models::config_model::AppConfig;
 
 use repository::hist_repo::HistRepo;
+use repository::invite_repo::InviteRepo;
 use repository::{
     user_repo::UserRepo,
     board_repo::BoardRepo,
 };
+use services::{engine::EnginePool, game_hub::GameHub, lobby::Lobby, reaper, sessions::Sessions, ws_server};
 
 use rocket::{
     http::Header,
@@ -28,9 +35,11 @@ use rocket::{
     Response
 };
 
-use rocket::fairing::{Fairing, Info, Kind};
+use rocket::fairing::{AdHoc, Fairing, Info, Kind};
+
+use clap::Parser;
 
-use command_line_interface::welcome;
+use command_line_interface::{finish, run, Cli, Command};
 
 pub struct Cors;
 
@@ -63,180 +72,50 @@ fn rocket() -> _ {
     //     allow_credentials: true,
     //     ..Default::default()
     // }.to_cors().unwrap();
-    let args: Vec<String> = env::args().collect();
-    if args.len() > 1 {
-        let cmd: &String = &args[1];
-        if cmd == "debug" {
-            loop {
-                welcome();
-                // Initialize the variables that are needed //
-                let mut player_1: String;
-                let mut player_2: String;
-                let mode: Vec<bool>;
-                let difficulty: i64;
-                let width: i64;
-                let height: i64;
-
-                // Get who is player 1 //
-                loop {
-                    player_1 = String::new();
-                    println!("Enter player 1 (Press ENTER as computer): ");
-                    io::stdout().flush().unwrap();
-                    let _ = io::stdin().read_line(&mut player_1).unwrap();
-                    player_1 = player_1.trim().to_string();
-                    if player_1 == "*" || player_1 == "**" {
-                        println!("You cannot use that name. Please choose another name.");
-                    } else {
-                        break;
-                    }
-                }
-                
-
-                // Get who is player 2 //
-                loop {
-                    player_2 = String::new();
-                    println!("Enter player 2 (Press ENTER as computer): ");
-                    io::stdout().flush().unwrap();
-                    let _ = io::stdin().read_line(&mut player_2).unwrap();
-                    player_2 = player_2.trim().to_string();
-                    if player_2 == "*" || player_2 == "**" {
-                        println!("You cannot use those names. Please choose another name.");
-                        continue;
-                    }
-                    if player_1 == "" {
-                        player_2 = "*".to_string();
-                    }
-                    break;
-                }
-
-                // Get the game mode //
-                loop {
-                    println!("Enter the game mode: ");
-                    let mut input: String = String::new();
-                    io::stdout().flush().unwrap();
-                    let _ = io::stdin().read_line(&mut input).unwrap();
-                    input = input.trim().to_string();
-                    if input.len() != 4 {
-                        println!("Input must contain exactly four characters.");
-                        continue;
-                    }
-                    if !input.chars().all(|c| {
-                        c == 'T' || c == 'O'
-                    }) {
-                        println!("Your input must contain either the characters T or O.");
-                        continue;
-                    }
-                    if input.chars().nth(0) == input.chars().nth(3) && input.chars().nth(1) == input.chars().nth(2) {
-                    } else {
-                        println!("The gamemode must be symmetric. Please try again.");
-                        continue;
-                    }
-                    mode = input.chars().map(|c| {
-                        match c {
-                            'T' => false,
-                            'O' => true,
-                            _ => false
-                        }
-                    })
-                    .collect();
-                    break;
-                }
-
-                // Get the difficulty //
-                loop {
-                    println!("Enter the difficulty level: ");
-                    let mut input: String = String::new();
-                    io::stdout().flush().unwrap();
-                    let _ = io::stdin().read_line(&mut input).unwrap();
-                    match input.trim().parse() {
-                        Ok(i) => {
-                            if i < 0 {
-                                println!("The difficulty must be at least 0.");
-                                continue;
-                            }
-                            difficulty = i;
-                            break;
-                        },
-                        Err(_) => {
-                            println!("Your input is invalid. Please try again.");
-                            continue;
-                        }
-                    }
-                }
-
-                // Get the width of the board //
-                loop {
-                    println!("Enter the width of the board: ");
-                    let mut input: String = String::new();
-                    io::stdout().flush().unwrap();
-                    let _ = io::stdin().read_line(&mut input).unwrap();
-                    match input.trim().parse() {
-                        Ok(i) => {
-                            if i < 1 {
-                                println!("The width of the board must be greater than 0.");
-                                continue;
-                            }
-                            width = i;
-                            break;
-                        },
-                        Err(_) => {
-                            println!("Your input is invalid. Please try again.");
-                            continue;
-                        }
-                    }
-                }
-
-                // Get the height of the board //
-                loop {
-                    println!("Enter the height of the board: ");
-                    let mut input: String = String::new();
-                    io::stdout().flush().unwrap();
-                    let _ = io::stdin().read_line(&mut input).unwrap();
-                    match input.trim().parse() {
-                        Ok(i) => {
-                            if i < 1 {
-                                println!("The height of the board must be greater than 0.");
-                                continue;
-                            }
-                            height = i;
-                            break;
-                        },
-                        Err(_) => {
-                            println!("Your input is invalid. Please try again.");
-                            continue;
-                        }
-                    }
-                }
-
-                let db: BoardRepo = BoardRepo::init();
-                let mut game_board: Board = match db.get_board(&Board::new(width.clone(), height.clone(), player_1.clone(), player_2.clone(), mode.clone(), difficulty.clone())) {
-                    Some(board) => board,
-                    None => Board::new(width.clone(), height.clone(), player_1.clone(), player_2.clone(), mode.clone(), difficulty.clone()),
-                };
-                let winner: String = game_board.host_game();
-                if player_2 == "*" {
-                    if winner == "" {
-                        println!("Computer 1 wins -- Congratulations!");
-                    } else {
-                        println!("Computer 2 wins -- Congratulations!");
-                    }
-                } else {
-                    game_board.print_congrats();
-                }
-            }
-        } else {
-            println!("Environment variable not recognized. Launching backend instead.")
-        }
-    }
+    match Cli::parse().command {
+        None | Some(Command::Serve) => (),
+        Some(command) => finish(run(command)),
+    };
 
     let db_user = UserRepo::init();
     let db_board_active = BoardRepo::init();
     let db_board_hist = HistRepo::init();
+    let db_invite = InviteRepo::init();
     rocket::build()
         .attach(Cors)
+        .attach(AdHoc::config::<AppConfig>())
+        .attach(AdHoc::on_liftoff("Websocket Server", |rocket| Box::pin(async move {
+            let config = rocket.state::<AppConfig>().unwrap().clone();
+            let hub = rocket.state::<GameHub>().unwrap().clone();
+            let db = rocket.state::<BoardRepo>().unwrap().clone();
+            let sessions = rocket.state::<Sessions>().unwrap().clone();
+            rocket::tokio::spawn(ws_server::serve(rocket.config().address, config, hub, db, sessions));
+        })))
+        .attach(AdHoc::on_liftoff("Abandoned Game Reaper", |rocket| Box::pin(async move {
+            let config = rocket.state::<AppConfig>().unwrap().clone();
+            let hub = rocket.state::<GameHub>().unwrap().clone();
+            let db = rocket.state::<BoardRepo>().unwrap().clone();
+            let hist = rocket.state::<HistRepo>().unwrap().clone();
+            let users = rocket.state::<UserRepo>().unwrap().clone();
+            let engine = rocket.state::<EnginePool>().unwrap().clone();
+            rocket::tokio::spawn(reaper::run(config, db, hist, users, hub, engine));
+        })))
+        .attach(AdHoc::on_liftoff("Engine Workers", |rocket| Box::pin(async move {
+            let config = rocket.state::<AppConfig>().unwrap().clone();
+            let hub = rocket.state::<GameHub>().unwrap().clone();
+            let db = rocket.state::<BoardRepo>().unwrap().clone();
+            let hist = rocket.state::<HistRepo>().unwrap().clone();
+            let users = rocket.state::<UserRepo>().unwrap().clone();
+            rocket.state::<EnginePool>().unwrap().start(config, db, hist, users, hub);
+        })))
         .manage(db_user)
         .manage(db_board_active)
         .manage(db_board_hist)
+        .manage(db_invite)
+        .manage(GameHub::default())
+        .manage(Lobby::default())
+        .manage(EnginePool::default())
+        .manage(Sessions::default())
         .mount("/", routes![create_user])
         .mount("/", routes![get_user])
         .mount("/", routes![get_all_users])
@@ -245,6 +124,32 @@ fn rocket() -> _ {
         .mount("/", routes![create_board])
         .mount("/", routes![get_board])
         .mount("/", routes![get_all_boards])
+        .mount("/", routes![get_board_state])
         .mount("/", routes![perform_move])
+        .mount("/", routes![check_clock])
+        .mount("/", routes![takeback])
+        .mount("/", routes![get_hint])
+        .mount("/", routes![analyze_position])
+        .mount("/", routes![get_engine_job])
+        .mount("/", routes![get_live_games])
+        .mount("/", routes![spectate_board])
+        .mount("/", routes![send_chat])
+        .mount("/", routes![get_chat])
         .mount("/", routes![get_hist])
+        .mount("/", routes![get_hist_game])
+        .mount("/", routes![export_game])
+        .mount("/", routes![replay_game])
+        .mount("/", routes![import_game])
+        .mount("/", routes![game_events])
+        .mount("/", routes![lobby_events])
+        .mount("/", routes![post_challenge])
+        .mount("/", routes![get_all_challenges])
+        .mount("/", routes![cancel_challenge])
+        .mount("/", routes![accept_challenge])
+        .mount("/", routes![join_queue])
+        .mount("/", routes![leave_queue])
+        .mount("/", routes![send_invite])
+        .mount("/", routes![accept_invite])
+        .mount("/", routes![decline_invite])
+        .mount("/", routes![get_invites])
 }
@@ -0,0 +1,65 @@
+use serde::{Serialize, Deserialize};
+
+use super::board_model::{RootScore, SearchResult};
+use super::general_model::GeneralStatus;
+
+pub const DEFAULT_ANALYZE_DEPTH: i64 = 8;
+pub const MAX_ANALYZE_DEPTH: i64 = 10;
+
+// how the game ends for the side to move when both sides play the best moves
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Win,
+    Draw,
+    Loss,
+    Unknown,  // The end of the game is deeper than the search looked.
+}
+
+// request model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnalyzeRequest {
+    pub position: String,  // A position string as written by Board::to_position_string.
+    #[serde(default)]
+    pub depth: i64,  // How many plies to look ahead, the default is used when left out.
+}
+
+// response model, scores are from the side of player 1 (x) like everywhere in the engine
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnalyzeResponse {
+    pub status: GeneralStatus,
+    pub position: String,
+    pub side_to_move: String,
+    pub outcome: Outcome,
+    pub best_moves: Vec<i64>,  // Every move as good as the best one.
+    pub search: SearchResult,
+}
+
+impl AnalyzeResponse {
+
+    pub fn failure(msg: &str) -> Self {
+        AnalyzeResponse {
+            status: GeneralStatus::failure(msg),
+            position: "".to_owned(),
+            side_to_move: "".to_owned(),
+            outcome: Outcome::Unknown,
+            best_moves: vec![],
+            search: SearchResult {
+                best_move: -1,
+                score: 0,
+                pv: vec![],
+                root_scores: vec![],
+                nodes: 0,
+                depth: 0,
+            },
+        }
+    }
+}
+
+// every root move scoring the same as the best move
+pub fn best_moves(search: &SearchResult) -> Vec<i64> {
+    search.root_scores.iter()
+        .filter(|r: &&RootScore| r.score == search.score)
+        .map(|r| r.col)
+        .collect()
+}
@@ -1,25 +1,95 @@
-use std::{cmp::{max, min}, collections::HashMap};
+use std::{
+    cmp::{max, min},
+    collections::HashMap,
+    sync::{atomic::{AtomicBool, Ordering}, Arc},
+    thread,
+    time::{Duration, Instant},
+};
 
 use serde::{Serialize, Deserialize};
 use rand::seq::SliceRandom;
+use mongodb::bson::oid::ObjectId;
 
 use super::general_model::GeneralStatus;
+use super::event_model::{GameEvent, GameEventEntry};
+use super::chat_model::ChatMessage;
 
 use std::io;
 //use std::io::Write;
 use chrono::prelude::*;
 
+// boards smaller than this cannot fit a line of four, larger ones are more than anyone can play on
+pub const MIN_SIZE: i64 = 4;
+pub const MAX_SIZE: i64 = 20;
+// hints each player may ask for in one game
+pub const MAX_HINTS: i64 = 3;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct HistBoard {
     pub board: Board,
     pub date: String,
     pub winner: String,
+    #[serde(default)]
+    pub reason: Option<EndReason>,  // Older records do not know why the game ended.
+    #[serde(default)]
+    pub analysis: Vec<MoveAnalysis>,  // Filled in by the engine a while after the game ended.
 }
 
 impl HistBoard {
 
+    /*
+    This should only be used as a dummy record for error cases.
+     */
+    pub fn empty() -> Self {
+        HistBoard {
+            board: Board::empty(),
+            date: "".to_owned(),
+            winner: "".to_owned(),
+            reason: None,
+            analysis: vec![],
+        }
+    }
+
+    /*
+    Rebuild the board the game started on from its settings and starting position.
+    Games recorded before moves were kept cannot be rebuilt.
+     */
+    pub fn start_board(&self) -> Result<Board, String> {
+
+        let b = &self.board;
+        let mut board = Board::new(b.width, b.height, b.player_1.clone(), b.player_2.clone(), b.mode.clone(), b.difficulty);
+        if !b.start_position.is_empty() {
+            board.set_position(&b.start_position)?;
+        }
+        if b.moves.is_empty() && b.move_count() > board.move_count() {
+            return Err("This game was recorded without its moves.".to_owned());
+        }
+        Ok(board)
+    }
+
+    /*
+    Rebuild the board after every ply of the game from its move list,
+    the first frame being the position the game started from.
+     */
+    pub fn replay(&self) -> Result<Vec<ReplayFrame>, String> {
+
+        let b = &self.board;
+        let mut board = self.start_board()?;
+        let mut frames = vec![ReplayFrame::new(&board, 0, None)];
+        for m in &b.moves {
+            if !board.allows_move(&m.col) {
+                return Err(format!("Move {} does not fit on the board.", m.ply));
+            }
+            board.perform_move(m.col, m.player.clone());
+            frames.push(ReplayFrame::new(&board, m.ply, m.score));
+        }
+        Ok(frames)
+    }
+
     pub fn new(board: Board, winner: String) -> Self {
         HistBoard {
+            reason: board.end_reason.clone(),
+            analysis: vec![],
             board,
             date: Local::now().to_string(),
             winner
@@ -27,8 +97,185 @@ impl HistBoard {
     }
 }
 
+// how good a move was compared to the best one the engine found
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MoveVerdict {
+    Best,
+    Inaccuracy,  // Still the same outcome, but it takes longer to win or less to lose.
+    Mistake,  // Gave away a win.
+    Blunder,  // Walked into a loss that could have been avoided.
+}
+
+// what the engine thinks of one move of a finished game, scores are from the side of the mover
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MoveAnalysis {
+    pub ply: i64,
+    pub player: String,
+    pub col: i64,
+    pub score: i64,
+    pub best_col: i64,
+    pub best_score: i64,
+    pub verdict: MoveVerdict,
+}
+
+// the board as it was after one ply of a finished game
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReplayFrame {
+    pub ply: i64,  // 0 for the starting position.
+    pub player: String,  // Who made the move, empty for the starting position.
+    pub row: i64,
+    pub col: i64,
+    pub board: Vec<Vec<String>>,
+    pub position: String,
+    pub score: Option<i64>,
+}
+
+impl ReplayFrame {
+
+    fn new(board: &Board, ply: i64, score: Option<i64>) -> Self {
+        let start = ply == 0;
+        ReplayFrame {
+            ply,
+            player: if start { "".to_owned() } else { board.last_player.clone() },
+            row: if start { -1 } else { board.last_row },
+            col: if start { -1 } else { board.last_col },
+            board: board.board.clone(),
+            position: board.to_position_string(),
+            score,
+        }
+    }
+}
+
+// why a game ended
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EndReason {
+    Connected,
+    Draw,
+    Resigned,
+    Timeout,
+    Abandoned,
+    Archived,
+}
+
+impl EndReason {
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EndReason::Connected => "connected",
+            EndReason::Draw => "draw",
+            EndReason::Resigned => "resigned",
+            EndReason::Timeout => "timeout",
+            EndReason::Abandoned => "abandoned",
+            EndReason::Archived => "archived",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "connected" => Some(EndReason::Connected),
+            "draw" => Some(EndReason::Draw),
+            "resigned" => Some(EndReason::Resigned),
+            "timeout" => Some(EndReason::Timeout),
+            "abandoned" => Some(EndReason::Abandoned),
+            "archived" => Some(EndReason::Archived),
+            _ => None,
+        }
+    }
+}
+
+// how long each player may think, a budget with an increment per move or a fixed time per move
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TimeControl {
+    Increment {
+        base_secs: i64,
+        increment_secs: i64,
+    },
+    PerMove {
+        move_secs: i64,
+    },
+}
+
+// the score of one move the player to move has
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RootScore {
+    pub col: i64,
+    pub score: i64,
+}
+
+// everything the engine found in one search
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchResult {
+    pub best_move: i64,  // -1 when the game is already over.
+    pub score: i64,  // Positive when player 1 is winning.
+    pub pv: Vec<i64>,  // The best move followed by the replies the engine expects.
+    pub root_scores: Vec<RootScore>,
+    pub nodes: i64,
+    pub depth: i64,
+}
+
+// asks a running search to give up, either when it is cancelled or once its time is up
+#[derive(Debug, Clone, Default)]
+pub struct SearchStop {
+    pub cancelled: Arc<AtomicBool>,
+    pub expired: Arc<AtomicBool>,  // Set once the deadline was seen to have passed.
+    pub deadline: Option<Instant>,
+}
+
+impl SearchStop {
+
+    // a search that gives up once the given time has passed
+    pub fn after(ms: u64) -> Self {
+        Self {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            expired: Arc::new(AtomicBool::new(false)),
+            deadline: Some(Instant::now() + Duration::from_millis(ms)),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    // look at the clock, once the deadline passed the search stays stopped
+    pub fn timed_out(&self) -> bool {
+        match self.deadline {
+            Some(d) if Instant::now() >= d => self.expired.store(true, Ordering::Relaxed),
+            _ => (),
+        };
+        self.expired.load(Ordering::Relaxed)
+    }
+
+    // whether the search should give up, without looking at the clock
+    pub fn is_set(&self) -> bool {
+        self.is_cancelled() || self.expired.load(Ordering::Relaxed)
+    }
+}
+
+// a move that was played, kept so that games can be taken back, replayed and analysed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MoveRecord {
+    pub player: String,
+    pub row: i64,
+    pub col: i64,
+    #[serde(default)]
+    pub ply: i64,  // 1 for the first move of the game.
+    #[serde(default)]
+    pub date: i64,  // Milliseconds since epoch.
+    #[serde(default)]
+    pub score: Option<i64>,  // Engine score of computer moves, positive when player 1 is winning.
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Board {
+    #[serde(default)]
+    pub id: String,  // Unique per game, boards posted by older clients come without one.
     pub width: i64,
     pub height: i64,
     pub board: Vec<Vec<String>>,
@@ -39,10 +286,41 @@ pub struct Board {
     pub player_2: String,
     pub mode: Vec<bool>,
     pub difficulty: i64,
+    #[serde(default)]
+    pub events: Vec<GameEventEntry>,  // Every event of the game so far, the n-th event has seq n.
+    #[serde(default)]
+    pub time_control: Option<TimeControl>,  // None means players may take as long as they like.
+    #[serde(default)]
+    pub clock_1_ms: i64,  // Time left of player 1, only used with a time control.
+    #[serde(default)]
+    pub clock_2_ms: i64,  // Time left of player 2, only used with a time control.
+    #[serde(default)]
+    pub turn_started_ms: i64,  // When the player to move started thinking, 0 until the first move is made.
+    #[serde(default)]
+    pub end_reason: Option<EndReason>,  // Only set once the game is over.
+    #[serde(default)]
+    pub last_activity_ms: i64,  // When the board was created or last moved on.
+    #[serde(default)]
+    pub moves: Vec<MoveRecord>,  // Every move played so far, in order.
+    #[serde(default)]
+    pub takeback_request: String,  // The player waiting for their takeback to be answered, empty if nobody is.
+    #[serde(default)]
+    pub takebacks: i64,  // How many takebacks were granted by the computer so far.
+    #[serde(default)]
+    pub start_position: String,  // The position string the game started from, empty for an empty board.
+    #[serde(default)]
+    pub hints_1: i64,  // Hints player 1 asked for so far.
+    #[serde(default)]
+    pub hints_2: i64,  // Hints player 2 asked for so far.
 }
 
 impl Board {
 
+    // whether a board of this size can be played on
+    pub fn valid_size(width: i64, height: i64) -> bool {
+        (MIN_SIZE..=MAX_SIZE).contains(&width) && (MIN_SIZE..=MAX_SIZE).contains(&height)
+    }
+
     pub fn opponent(&self, player: &String) -> String {
         if player.eq(&self.player_1) {
             self.player_2.clone()
@@ -58,25 +336,25 @@ impl Board {
             board_str.push('|');
             for col in 0..self.width {
                 if self.board[row as usize][col as usize] == self.player_1 {
-                    board_str.push_str("T");
+                    board_str.push('T');
                 } else if self.board[row as usize][col as usize] == self.player_2 {
-                    board_str.push_str("O");
+                    board_str.push('O');
                 } else {
                     board_str.push_str(&self.board[row as usize][col as usize]);
                 }
-                board_str.push_str("|");
+                board_str.push('|');
             }
-            board_str.push_str("\n");
+            board_str.push('\n');
         }
         // Line of dashes
         for _ in 0..(self.width*2+1) {
-            board_str.push_str("-");
+            board_str.push('-');
         }
-        board_str.push_str("\n");
+        board_str.push('\n');
         for col in 0..self.width {
             board_str.push_str(&format!(" {}", col));
         }
-        return board_str;
+        board_str
     }
 
     /*
@@ -86,11 +364,21 @@ impl Board {
         let mut allowable_moves: Vec<i64> = Vec::new();
         for col in 0..self.width {
             if self.allows_move(&col){
-                allowable_moves.push(col.clone());
+                allowable_moves.push(col);
             }
         }
         allowable_moves.shuffle(&mut rand::thread_rng());
-        return allowable_moves;
+        allowable_moves
+    }
+
+    /*
+    Get the allowed moves from the center column outwards, the order the search tries them in.
+    Unlike available_moves the order is always the same.
+     */
+    pub fn ordered_moves(&self) -> Vec<i64> {
+        let mut moves: Vec<i64> = (0..self.width).filter(|col| self.allows_move(col)).collect();
+        moves.sort_by_key(|col| (2 * col - (self.width - 1)).abs());
+        moves
     }
 
     /*
@@ -98,13 +386,9 @@ impl Board {
      */
     pub fn allows_move(&self, col: &i64) -> bool {
         if *col < 0 || *col >= self.width {
-            return false;
+            false
         } else {
-            if self.board[0][(*col) as usize] == ' '.to_string() {
-                return true;
-            } else {
-                return false;
-            }
+            self.board[0][(*col) as usize] == ' '.to_string()
         }
     }
 
@@ -115,8 +399,8 @@ impl Board {
         for row in (0..self.height).rev() {
             if self.board[row as usize][col as usize] == ' '.to_string() {
                 self.board[row as usize][col as usize] = ox.clone();
-                self.last_row = row.clone();
-                self.last_col = col.clone();
+                self.last_row = row;
+                self.last_col = col;
                 self.last_player = ox.clone();
                 return;
             }
@@ -128,6 +412,7 @@ impl Board {
      */
     pub fn empty() -> Self {
         Board {
+            id: "".to_owned(),
             width: 0,
             height: 0,
             board: vec![],
@@ -138,6 +423,19 @@ impl Board {
             player_2: ' '.to_string(),
             mode: vec![],
             difficulty: 1,
+            events: vec![],
+            time_control: None,
+            clock_1_ms: 0,
+            clock_2_ms: 0,
+            turn_started_ms: 0,
+            end_reason: None,
+            last_activity_ms: 0,
+            moves: vec![],
+            takeback_request: "".to_owned(),
+            takebacks: 0,
+            start_position: "".to_owned(),
+            hints_1: 0,
+            hints_2: 0,
         }
     }
 
@@ -156,7 +454,8 @@ impl Board {
                 board_init[r as usize].push(' '.to_string());
             }
         }
-        return Board {
+        Board {
+            id: ObjectId::new().to_hex(),
             width: w,
             height: h,
             board: board_init.clone(),
@@ -167,9 +466,335 @@ impl Board {
             player_2: p2.to_string(),
             mode: m,
             difficulty: d,
+            events: vec![],
+            time_control: None,
+            clock_1_ms: 0,
+            clock_2_ms: 0,
+            turn_started_ms: 0,
+            end_reason: None,
+            last_activity_ms: Utc::now().timestamp_millis(),
+            moves: vec![],
+            takeback_request: "".to_owned(),
+            takebacks: 0,
+            start_position: "".to_owned(),
+            hints_1: 0,
+            hints_2: 0,
         }
     }
 
+    /*
+    Set both clocks to the time the time control starts with.
+    The clocks only start running once the first move is made.
+     */
+    pub fn start_clocks(&mut self) {
+        let start = match &self.time_control {
+            Some(TimeControl::Increment { base_secs, .. }) => base_secs * 1000,
+            Some(TimeControl::PerMove { move_secs }) => move_secs * 1000,
+            None => return,
+        };
+        self.clock_1_ms = start;
+        self.clock_2_ms = start;
+        self.turn_started_ms = 0;
+    }
+
+    /*
+    Time left of a player in milliseconds at the given moment, -1 without a time control.
+    Only the clock of the player to move is running.
+     */
+    pub fn time_left(&self, player: &String, now_ms: i64) -> i64 {
+        if self.time_control.is_none() {
+            return -1;
+        }
+        let clock = if player.eq(&self.player_1) { self.clock_1_ms } else { self.clock_2_ms };
+        if self.turn_started_ms > 0 && player.eq(&self.get_next_player()) {
+            return max(0, clock - (now_ms - self.turn_started_ms));
+        }
+        clock
+    }
+
+    /*
+    Get the player to move if they ran out of time.
+    The computer never runs out of time.
+     */
+    pub fn flagged_player(&self, now_ms: i64) -> Option<String> {
+        let player = self.get_next_player();
+        if self.time_control.is_none() || self.turn_started_ms == 0 || player == "*" {
+            return None;
+        }
+        match self.time_left(&player, now_ms) {
+            0 => Some(player),
+            _ => None,
+        }
+    }
+
+    /*
+    Stop the clock of the player who just moved and start the one of their opponent.
+    The thinking time is charged and the increment added, or the clock is reset
+    when there is a fixed time per move.
+     */
+    pub fn press_clock(&mut self, player: &String, now_ms: i64) {
+        let used = if self.turn_started_ms > 0 { now_ms - self.turn_started_ms } else { 0 };
+        let clock = if player.eq(&self.player_1) { self.clock_1_ms } else { self.clock_2_ms };
+        let clock = match &self.time_control {
+            Some(TimeControl::Increment { increment_secs, .. }) => max(0, clock - used) + increment_secs * 1000,
+            Some(TimeControl::PerMove { move_secs }) => move_secs * 1000,
+            None => return,
+        };
+        if player.eq(&self.player_1) {
+            self.clock_1_ms = clock;
+        } else {
+            self.clock_2_ms = clock;
+        }
+        self.turn_started_ms = now_ms;
+    }
+
+    /*
+    Perform a move of a real game and remember it in the move list,
+    the score is only known for moves found by the engine.
+    Any pending takeback request is dropped since the position changed.
+     */
+    pub fn record_move(&mut self, col: i64, ox: String, score: Option<i64>) {
+        self.perform_move(col, ox.clone());
+        self.moves.push(MoveRecord {
+            player: ox,
+            row: self.last_row,
+            col: self.last_col,
+            ply: self.moves.len() as i64 + 1,
+            date: Utc::now().timestamp_millis(),
+            score,
+        });
+        self.takeback_request = "".to_owned();
+    }
+
+    /*
+    Number of moves to take back so that it is the turn of the player again,
+    0 if the player has not moved yet.
+     */
+    pub fn takeback_len(&self, player: &String) -> i64 {
+        match self.moves.iter().rev().position(|m| m.player.eq(player)) {
+            Some(i) => i as i64 + 1,
+            None => 0,
+        }
+    }
+
+    /*
+    Take back the last n moves and restore the last move from the move list.
+    Returns false and leaves the board alone if fewer moves were played.
+     */
+    pub fn take_back(&mut self, n: i64) -> bool {
+        if n < 1 || n as usize > self.moves.len() {
+            return false;
+        }
+        for _ in 0..n {
+            let m = self.moves.pop().unwrap();
+            self.undo_move(m.col);
+        }
+        match self.moves.last() {
+            Some(m) => {
+                self.last_row = m.row;
+                self.last_col = m.col;
+                self.last_player = m.player.clone();
+            },
+            None => {
+                self.last_row = -1;
+                self.last_col = -1;
+                self.last_player = self.player_2.clone();
+            },
+        };
+        self.takeback_request = "".to_owned();
+        true
+    }
+
+    /*
+    How many takebacks the computer grants in one game, fewer on higher difficulties.
+     */
+    pub fn takeback_limit(&self) -> i64 {
+        max(0, 6 - (self.difficulty + 1) / 2)
+    }
+
+    /*
+    Write the position down on one line: size, mode, rows from top to bottom and side to move.
+    Checkers of player 1 are x, those of player 2 are o and runs of empty cells are counted,
+    so the empty standard board is "7x6 TTTT 7/7/7/7/7/7 x".
+     */
+    pub fn to_position_string(&self) -> String {
+
+        let mode: String = self.mode.iter().map(|o| if *o { 'O' } else { 'T' }).collect();
+        let rows: Vec<String> = self.board.iter()
+            .map(|row| {
+                let (mut text, mut empty) = (String::new(), 0);
+                for cell in row {
+                    if cell == " " {
+                        empty += 1;
+                        continue;
+                    }
+                    if empty > 0 {
+                        text.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    text.push(if cell.eq(&self.player_1) { 'x' } else { 'o' });
+                }
+                if empty > 0 {
+                    text.push_str(&empty.to_string());
+                }
+                text
+            })
+            .collect();
+        let side = if self.get_next_player() == self.player_1 { "x" } else { "o" };
+
+        format!("{}x{} {} {} {}", self.width, self.height, mode, rows.join("/"), side)
+    }
+
+    /*
+    Set the board up from a position string written by to_position_string.
+    The position has to be one that can come up in a game: no checker may float above an empty
+    cell, player 1 has as many checkers as player 2 or one more depending on who is to move,
+    and nobody may have won already.
+     */
+    pub fn set_position(&mut self, position: &str) -> Result<(), String> {
+
+        let parts: Vec<&str> = position.split_whitespace().collect();
+        let (size, mode, rows, side) = match parts.as_slice() {
+            [size, mode, rows, side] => (*size, *mode, *rows, *side),
+            _ => return Err("A position needs a size, a mode, the rows and the side to move.".to_owned()),
+        };
+
+        let (width, height) = match size.split_once('x').map(|(w, h)| (w.parse::<i64>(), h.parse::<i64>())) {
+            Some((Ok(w), Ok(h))) if Board::valid_size(w, h) => (w, h),
+            _ => return Err(format!("The size must look like 7x6 and be between {0}x{0} and {1}x{1}.", MIN_SIZE, MAX_SIZE)),
+        };
+        if mode.len() != 4 || !mode.chars().all(|c| c == 'T' || c == 'O') {
+            return Err("The mode must be four letters T or O.".to_owned());
+        }
+
+        let rows: Vec<&str> = rows.split('/').collect();
+        if rows.len() as i64 != height {
+            return Err("The number of rows does not match the size.".to_owned());
+        }
+        let mut board: Vec<Vec<String>> = vec![];
+        for row in rows {
+            let (mut cells, mut empty): (Vec<String>, i64) = (vec![], 0);
+            for c in row.chars() {
+                match c {
+                    '0'..='9' => {
+                        // a run may not be longer than what is left of the row, so it cannot grow without bound
+                        empty = match empty.checked_mul(10).and_then(|e| e.checked_add(c.to_digit(10).unwrap() as i64)) {
+                            Some(e) if e <= width - cells.len() as i64 => e,
+                            _ => return Err("The length of a row does not match the size.".to_owned()),
+                        };
+                    },
+                    'x' | 'o' => {
+                        if cells.len() as i64 + empty >= width {
+                            return Err("The length of a row does not match the size.".to_owned());
+                        }
+                        cells.extend((0..empty).map(|_| ' '.to_string()));
+                        empty = 0;
+                        cells.push(if c == 'x' { self.player_1.clone() } else { self.player_2.clone() });
+                    },
+                    _ => return Err(format!("Unknown cell: {}", c)),
+                };
+            }
+            cells.extend((0..empty).map(|_| ' '.to_string()));
+            if cells.len() as i64 != width {
+                return Err("The length of a row does not match the size.".to_owned());
+            }
+            board.push(cells);
+        }
+
+        // a checker always falls onto the one below it
+        for rows in board.windows(2) {
+            if rows[0].iter().zip(&rows[1]).any(|(above, below)| above != " " && below == " ") {
+                return Err("A checker is floating above an empty cell.".to_owned());
+            }
+        }
+
+        let count = |player: &String| board.iter().flatten().filter(|cell| *cell == player).count() as i64;
+        let (x, o) = (count(&self.player_1), count(&self.player_2));
+        let last_player = match side {
+            "x" if x == o => self.player_2.clone(),
+            "o" if x == o + 1 => self.player_1.clone(),
+            "x" | "o" => return Err("The number of checkers does not match the side to move.".to_owned()),
+            _ => return Err("The side to move must be x or o.".to_owned()),
+        };
+
+        let mut positioned = self.clone();
+        positioned.width = width;
+        positioned.height = height;
+        positioned.mode = mode.chars().map(|c| c == 'O').collect();
+        positioned.board = board;
+        positioned.last_player = last_player;
+        if positioned.is_draw() || positioned.any_winner() {
+            return Err("The game is already over in this position.".to_owned());
+        }
+
+        positioned.last_row = -1;
+        positioned.last_col = -1;
+        positioned.start_position = positioned.to_position_string();
+        *self = positioned;
+        Ok(())
+    }
+
+    /*
+    Check every line on the board for a winner, not only the ones through the last move.
+     */
+    fn any_winner(&self) -> bool {
+        let mut b = self.clone();
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let cell = self.board[row as usize][col as usize].clone();
+                if cell == " " {
+                    continue;
+                }
+                b.last_row = row;
+                b.last_col = col;
+                b.last_player = cell;
+                if b.has_winner() {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /*
+    How many hints a player asked for in this game.
+     */
+    pub fn hints(&self, player: &String) -> i64 {
+        if player.eq(&self.player_1) { self.hints_1 } else { self.hints_2 }
+    }
+
+    /*
+    Count a hint given to the player, returns false and counts nothing once they used up all of theirs.
+     */
+    pub fn take_hint(&mut self, player: &String) -> bool {
+        if self.hints(player) >= MAX_HINTS {
+            return false;
+        }
+        if player.eq(&self.player_1) {
+            self.hints_1 += 1;
+        } else {
+            self.hints_2 += 1;
+        }
+        true
+    }
+
+    /*
+    The chat of the game, read from its event log which is the only place it is kept.
+     */
+    pub fn chat(&self) -> Vec<ChatMessage> {
+        self.events.iter()
+            .filter_map(|e| match &e.event {
+                GameEvent::Chat { sender, text, date, spectator } => Some(ChatMessage {
+                    sender: sender.clone(),
+                    text: text.clone(),
+                    date: *date,
+                    spectator: *spectator,
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
     /*
     Should only be used in alpha-beta.
      */
@@ -182,36 +807,45 @@ impl Board {
         }
     }
 
+    /*
+    Count the checkers on the board.
+     */
+    pub fn move_count(&self) -> i64 {
+        self.board.iter()
+            .map(|row| row.iter().filter(|cell| *cell != " ").count() as i64)
+            .sum()
+    }
+
     /*
     Check if game over.
      */
     pub fn is_terminal(&self) -> bool {
-        return self.has_winner() || self.is_draw();
+        self.has_winner() || self.is_draw()
     }
 
     pub fn get_next_player(&self) -> String {
-        return self.pattern(&self.last_player, &true);
+        self.pattern(&self.last_player, &true)
     }
 
-    fn pattern(&self, ox: &String, bit: &bool) -> String {
+    fn pattern(&self, ox: &str, bit: &bool) -> String {
         let rev = HashMap::from([
             (self.player_1.clone(), self.player_2.clone()),
             (self.player_2.clone(), self.player_1.clone()),
         ]);
         match bit {
-            false => ox.clone(),
-            true => rev.get(ox.as_str()).unwrap().to_string(),
+            false => ox.to_owned(),
+            true => rev.get(ox).unwrap().to_string(),
         }
     }
 
-    fn pattern_enemy(&self, ox: &String, bit: &bool) -> String {
+    fn pattern_enemy(&self, ox: &str, bit: &bool) -> String {
         let rev = HashMap::from([
             (self.player_1.clone(), self.player_2.clone()),
             (self.player_2.clone(), self.player_1.clone()),
         ]);
         match bit {
-            false => rev.get(ox.as_str()).unwrap().to_string(),
-            true => ox.clone(),
+            false => rev.get(ox).unwrap().to_string(),
+            true => ox.to_owned(),
         }
     }
 
@@ -306,14 +940,14 @@ impl Board {
         }
 
         // no winner if none of the above is satisfied
-        return (false, "".to_owned());
+        (false, "".to_owned())
     }
 
     /*
     Check if it is a draw.
      */
     pub fn is_draw(&self) -> bool {
-        return self.available_moves().len() == 0;
+        self.available_moves().is_empty()
     }
 
     /*
@@ -344,7 +978,7 @@ impl Board {
     Prints out who won the game and the final game board.
      */
     pub fn print_congrats(&self) {
-        if self.last_player == "" {
+        if self.last_player.is_empty() {
             println!("Computer wins -- Congratulations!");
         } else {
             println!("{} wins -- Congratulations!", self.last_player);
@@ -353,19 +987,21 @@ impl Board {
     }
 
     /*
-    Hosts a game which can be played between two players.
+    Hosts a game which can be played between two players, from the player to move on.
+    'on_move' sees the board after every move, such as to save the game.
      */
-    pub fn host_game(&mut self) -> String {
+    pub fn host_game(&mut self, on_move: &mut dyn FnMut(&Board)) -> String {
         println!("Welcome!");
         // let mut game_over: bool = false;
-        let mut ox: String = self.player_1.clone();
+        let mut ox: String = self.get_next_player();
         loop {
             println!("{}", self.print());
-            if ox == "" || ox == "*" {
-                let (_, col_move): (i64, i64) = self.alpha_beta(ox.clone(), i64::MIN, i64::MAX, self.difficulty);
-                self.perform_move(col_move, ox.clone());
+            if ox.is_empty() || ox == "*" {
+                let result = self.search(self.difficulty, 1);
+                let col_move = result.best_move;
+                self.record_move(col_move, ox.clone(), None);
                 if self.player_2 == "*" {  // This checks if we are playing a computer vs computer game.
-                    if ox == "" {
+                    if ox.is_empty() {
                         println!("Computer 1 performed move {}.", col_move);
                     } else {
                         println!("Computer 2 performed move {}.", col_move);
@@ -375,8 +1011,9 @@ impl Board {
                 }
             } else {
                 let col_move: i64 = self.get_player_move(ox.clone());
-                self.perform_move(col_move, ox.clone());
+                self.record_move(col_move, ox.clone(), None);
             }
+            on_move(self);
             if self.has_winner() {
                 return ox;
             }
@@ -399,14 +1036,14 @@ impl Board {
         let (hw, winner) = self._has_winner();
         if hw {
             if winner == self.player_1 {
-                return 1;
+                1
             } else {
-                return -1;
+                -1
             }
         } else if self.is_draw() {
-            return 0;
+            0
         } else {
-            return 2  // In this case the board does not represent a terminal state and will return the dummy value 2.
+            2  // In this case the board does not represent a terminal state and will return the dummy value 2.
         }
     }
 
@@ -414,22 +1051,29 @@ impl Board {
         This function receives a player denoted as either 'X' or 'O' representing the current
         player who is to move. The parameters 'alpha' and 'beta' are used to prune the search
         tree. The parameter 'ply' represents the depth of the depth. Increasing the 'ply' value
-        returns better moves but also takes longer.
+        returns better moves but also takes longer. Every position visited is counted in 'nodes'.
+        The search gives up once 'stop' is set, whatever it returns then is meaningless.
 
         The function returns three values:
         1. the score of the optimal move for the player who is to act;
-        2. the optimal move
+        2. the optimal move;
+        3. the principal variation, the optimal move followed by the replies expected to it.
     */
-    pub fn alpha_beta(&mut self, player: String, mut alpha: i64, mut beta: i64, ply: i64) -> (i64, i64) {
+    pub fn alpha_beta(&mut self, player: String, mut alpha: i64, mut beta: i64, ply: i64, nodes: &mut i64, stop: &SearchStop) -> (i64, i64, Vec<i64>) {
 
+        *nodes += 1;
+        // looking at the clock for every position would slow the search down
+        if (*nodes % 1024 == 0 && stop.timed_out()) || stop.is_set() {
+            return (0, -1, vec![]);
+        }
         if self.is_terminal() {
             let game_value = self.game_value();
             if game_value < 0 {
-                return (game_value - ply, 0);
+                return (game_value - ply, 0, vec![]);
             } else if game_value > 0 {
-                return (game_value + ply, 0);
+                return (game_value + ply, 0, vec![]);
             }
-            return (self.game_value(), 0);
+            return (self.game_value(), 0, vec![]);
         }
 
         let init_score = HashMap::from([
@@ -438,67 +1082,163 @@ impl Board {
         ]);
 
         if ply <= 0 {
-            return (0, 0);
+            return (0, 0, vec![]);
         }
         
-        let ((mut score, next_player), mut mov) = (init_score.get(&player).unwrap(), -1);
-
-        for m in self.available_moves() {
-            self.perform_move(m.clone(), player.clone());
-            let (m_score, _) = self.clone().alpha_beta(next_player.to_string(), alpha, beta, ply-1);
+        let ((mut score, next_player), mut mov, mut pv) = (init_score.get(&player).unwrap(), -1, vec![]);
 
-            if ply == self.difficulty {
-                println!("{:?} - {:?}", m, m_score);
-            }
+        for m in self.ordered_moves() {
+            self.perform_move(m, player.clone());
+            let (m_score, _, m_pv) = self.clone().alpha_beta(next_player.to_string(), alpha, beta, ply-1, nodes, stop);
 
             if player == self.player_1.clone() {
 
-                //if score != max(score.clone(), m_score) {
-                    //score = m_score.clone();
-                    //mov = m.clone();
-                //}
-                score = max(score.clone(), m_score);
+                score = max(score, m_score);
                 if beta <= score {
-                    self.undo_move(m.clone());
-                    return (score.clone(), mov);
+                    self.undo_move(m);
+                    return (score, mov, pv);
                 }
-                //alpha = max(alpha.clone(), score.clone());
                 if score > alpha {
-                    alpha = score.clone();
-                    mov = m.clone();
+                    alpha = score;
+                    mov = m;
+                    pv = [vec![m], m_pv].concat();
                 }
-            }
-
-            if player == self.player_2.clone() {
+            } else if player == self.player_2.clone() {
 
-                //if score != min(score, m_score) {
-                    //score = m_score.clone();
-                    //mov = m.clone();
-                //}
-                score = min(score.clone(), m_score);
+                score = min(score, m_score);
                 if alpha >= score {
-                    self.undo_move(m.clone());
-                    return (score.clone(), mov);
+                    self.undo_move(m);
+                    return (score, mov, pv);
                 }
-                //beta = min(beta.clone(), score.clone());
                 if score < beta {
-                    beta = score.clone();
-                    mov = m.clone();
+                    beta = score;
+                    mov = m;
+                    pv = [vec![m], m_pv].concat();
                 }
             }
 
-            self.undo_move(m.clone());
+            self.undo_move(m);
+        }
+
+        (score, mov, pv)
+    }
+
+    /*
+    Search the position for the player to move and tell everything the engine found:
+    the best move, its score, the line it expects to follow, the score of every move
+    the player has, how many positions were visited and how deep it looked.
+    Every move at the root is searched with a full window so that its score is exact,
+    which lets the root moves be shared out among several threads. Moves are always
+    searched in the same order, so the result does not depend on the number of threads
+    and the first of equally good moves is the best one.
+    Scores are positive when player 1 is winning, like alpha_beta.
+     */
+    pub fn search(&self, depth: i64, threads: usize) -> SearchResult {
+        self.search_until(depth, threads, &SearchStop::default())
+    }
+
+    /*
+    Search like search, but give up once 'stop' is set.
+    The result of a search that gave up is meaningless, the caller checks 'stop' afterwards.
+     */
+    pub fn search_until(&self, depth: i64, threads: usize, stop: &SearchStop) -> SearchResult {
+
+        let player = self.get_next_player();
+        let opponent = self.opponent(&player);
+        let mut result = SearchResult {
+            best_move: -1,
+            score: 0,
+            pv: vec![],
+            root_scores: vec![],
+            nodes: 1,
+            depth,
+        };
+        if self.is_terminal() || depth <= 0 {
+            return result;
+        }
+
+        // search one root move, giving its score, its line and the positions visited
+        let search_move = |m: i64| {
+            let mut b = self.clone();
+            b.perform_move(m, player.clone());
+            let mut nodes = 0;
+            let (score, _, pv) = b.alpha_beta(opponent.clone(), i64::MIN, i64::MAX, depth - 1, &mut nodes, stop);
+            (m, score, pv, nodes)
+        };
+
+        let moves = self.ordered_moves();
+        let threads = max(1, min(threads, moves.len()));
+        let mut lines: Vec<(i64, i64, Vec<i64>, i64)> = match threads {
+            1 => moves.iter().map(|m| search_move(*m)).collect(),
+            _ => thread::scope(|scope| {
+                let search_move = &search_move;
+                let handles: Vec<_> = (0..threads)
+                    .map(|t| {
+                        let share: Vec<i64> = moves.iter().skip(t).step_by(threads).cloned().collect();
+                        scope.spawn(move || share.into_iter().map(search_move).collect::<Vec<_>>())
+                    })
+                    .collect();
+                handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+            }),
+        };
+        lines.sort_by_key(|line| moves.iter().position(|m| *m == line.0));
+
+        for (m, score, pv, nodes) in lines {
+            result.nodes += nodes;
+            result.root_scores.push(RootScore { col: m, score });
+
+            let better = if player == self.player_1 { score > result.score } else { score < result.score };
+            if result.best_move == -1 || better {
+                result.best_move = m;
+                result.score = score;
+                result.pv = [vec![m], pv].concat();
+            }
+        }
+        result.root_scores.sort_by_key(|r| r.col);
+        result
+    }
+
+    /*
+    Count the positions reached after exactly 'depth' more moves, a game that is over earlier
+    is not played on. Used to check that moves are generated and taken back correctly.
+     */
+    pub fn perft(&mut self, depth: i64) -> u64 {
+
+        if depth <= 0 {
+            return 1;
+        }
+        if self.is_terminal() {
+            return 0;
         }
 
-        return (score.clone(), mov);
+        let player = self.get_next_player();
+        let last = (self.last_row, self.last_col, self.last_player.clone());
+        let mut count = 0;
+        for m in self.ordered_moves() {
+            self.perform_move(m, player.clone());
+            count += self.perft(depth - 1);
+            self.undo_move(m);
+            (self.last_row, self.last_col, self.last_player) = last.clone();
+        }
+        count
     }
 }
 
 // request model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateBoardRequest {
+    #[serde(flatten)]
+    pub board: Board,
+    #[serde(default)]
+    pub position: String,  // Start from this position string instead of an empty board.
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PerformMoveRequest {
     pub board_info: Board,
     pub col: i64,
+    #[serde(default)]
+    pub token: String,  // Given at login to the player who is moving or giving up.
 }
 
 // response model
@@ -511,6 +1251,11 @@ pub struct PerformMoveResponse {
     pub cmput_row: i64,
     pub cmput_col: i64,
     pub winner: String,
+    pub reason: Option<EndReason>,
+    pub clock_1_ms: i64,
+    pub clock_2_ms: i64,
+    #[serde(default)]
+    pub job_id: String,  // Engine job computing the computer reply, empty when nobody has to reply.
 }
 
 impl PerformMoveResponse {
@@ -528,7 +1273,62 @@ impl PerformMoveResponse {
             human_col: human_move.1,
             cmput_row: cmput_move.0,
             cmput_col: cmput_move.1,
-            winner
+            winner,
+            reason: board.end_reason.clone(),
+            clock_1_ms: board.time_left(&board.player_1, Utc::now().timestamp_millis()),
+            clock_2_ms: board.time_left(&board.player_2, Utc::now().timestamp_millis()),
+            job_id: "".to_owned(),
+        }
+    }
+}
+
+// what a player does about a takeback
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TakebackAction {
+    Request,
+    Accept,
+    Decline,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TakebackRequest {
+    pub name: String,
+    pub pwd: String,
+    pub action: TakebackAction,
+}
+
+// a player logging in to see everything about their own game
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlayerRequest {
+    pub name: String,
+    pub pwd: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HintRequest {
+    pub name: String,
+    pub pwd: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HintResponse {
+    pub status: GeneralStatus,
+    pub col: i64,
+    pub score: i64,  // From the side of the player asking, positive when they can force a win.
+    pub pv: Vec<i64>,  // The moves the engine expects to follow, starting with the hint.
+    pub hints_left: i64,
+}
+
+impl HintResponse {
+
+    pub fn failure(msg: &str) -> Self {
+        HintResponse {
+            status: GeneralStatus::failure(msg),
+            col: -1,
+            score: 0,
+            pv: vec![],
+            hints_left: 0,
         }
     }
 }
@@ -539,14 +1339,298 @@ pub struct GeneralBoardResponse {
     pub board: Board,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ClockResponse {
+    pub status: GeneralStatus,
+    pub clock_1_ms: i64,
+    pub clock_2_ms: i64,
+    pub winner: String,
+    pub reason: Option<EndReason>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GetAllBoardResponse {
     pub status: GeneralStatus,
-    pub all_boards: Vec<Board>,
+    pub all_boards: Vec<SpectatorView>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GeneralHistResponse {
+    pub status: GeneralStatus,
+    pub hist: HistBoard,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReplayResponse {
+    pub status: GeneralStatus,
+    pub hist: HistBoard,
+    pub frames: Vec<ReplayFrame>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GetHistResponse {
     pub status: GeneralStatus,
     pub hist: Vec<HistBoard>,
-}
\ No newline at end of file
+}
+
+// what the list of running games shows about one game
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LiveGame {
+    pub id: String,
+    pub player_1: String,
+    pub player_2: String,
+    pub width: i64,
+    pub height: i64,
+    pub mode: Vec<bool>,
+    pub difficulty: i64,
+    pub move_count: i64,
+    pub spectators: i64,
+}
+
+impl LiveGame {
+
+    pub fn new(board: &Board, spectators: i64) -> Self {
+        Self {
+            id: board.id.clone(),
+            player_1: board.player_1.clone(),
+            player_2: board.player_2.clone(),
+            width: board.width,
+            height: board.height,
+            mode: board.mode.clone(),
+            difficulty: board.difficulty,
+            move_count: board.move_count(),
+            spectators,
+        }
+    }
+}
+
+/*
+A read-only copy of a board for spectators.
+Only what can be seen on the screen of the players is copied, never the event log.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpectatorView {
+    pub id: String,
+    pub player_1: String,
+    pub player_2: String,
+    pub width: i64,
+    pub height: i64,
+    pub mode: Vec<bool>,
+    pub board: Vec<Vec<String>>,
+    pub last_row: i64,
+    pub last_col: i64,
+    pub next_player: String,
+    pub position: String,
+    pub clock_1_ms: i64,
+    pub clock_2_ms: i64,
+    pub spectators: i64,
+}
+
+impl SpectatorView {
+
+    pub fn empty() -> Self {
+        Self {
+            id: "".to_owned(),
+            player_1: "".to_owned(),
+            player_2: "".to_owned(),
+            width: 0,
+            height: 0,
+            mode: vec![],
+            board: vec![],
+            last_row: -1,
+            last_col: -1,
+            next_player: "".to_owned(),
+            position: "".to_owned(),
+            clock_1_ms: -1,
+            clock_2_ms: -1,
+            spectators: 0,
+        }
+    }
+
+    pub fn new(board: &Board, spectators: i64) -> Self {
+        let now = Utc::now().timestamp_millis();
+        Self {
+            id: board.id.clone(),
+            player_1: board.player_1.clone(),
+            player_2: board.player_2.clone(),
+            width: board.width,
+            height: board.height,
+            mode: board.mode.clone(),
+            board: board.board.clone(),
+            last_row: board.last_row,
+            last_col: board.last_col,
+            next_player: board.get_next_player(),
+            position: board.to_position_string(),
+            clock_1_ms: board.time_left(&board.player_1, now),
+            clock_2_ms: board.time_left(&board.player_2, now),
+            spectators,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GetLiveGamesResponse {
+    pub status: GeneralStatus,
+    pub live_games: Vec<LiveGame>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BoardViewResponse {
+    pub status: GeneralStatus,
+    pub board: SpectatorView,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpectateResponse {
+    pub status: GeneralStatus,
+    pub view: SpectatorView,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(cols: &[i64]) -> Board {
+        let mut board = Board::new(7, 6, "alice".to_owned(), "bob".to_owned(), vec![false; 4], 1);
+        for col in cols {
+            let player = board.get_next_player();
+            board.record_move(*col, player, None);
+        }
+        board
+    }
+
+    #[test]
+    fn each_player_gets_a_limited_number_of_hints() {
+        let mut board = game(&[3]);
+        let (alice, bob) = ("alice".to_owned(), "bob".to_owned());
+        for _ in 0..MAX_HINTS {
+            assert!(board.take_hint(&bob));
+        }
+        assert!(!board.take_hint(&bob));
+        assert_eq!(board.hints(&bob), MAX_HINTS);
+        assert_eq!(board.hints(&alice), 0);
+        assert!(board.take_hint(&alice));
+        assert_eq!(board.hints(&alice), 1);
+    }
+
+    fn timed_game(time_control: TimeControl) -> Board {
+        let mut board = game(&[]);
+        board.time_control = Some(time_control);
+        board.start_clocks();
+        board
+    }
+
+    #[test]
+    fn clock_only_runs_for_the_player_to_move() {
+        let mut board = timed_game(TimeControl::Increment { base_secs: 60, increment_secs: 2 });
+        let (alice, bob) = ("alice".to_owned(), "bob".to_owned());
+        assert_eq!(board.time_left(&alice, 5_000), 60_000);
+        board.perform_move(3, alice.clone());
+        board.press_clock(&alice, 1_000);
+        assert_eq!(board.time_left(&alice, 11_000), 62_000);
+        assert_eq!(board.time_left(&bob, 11_000), 50_000);
+        assert_eq!(game(&[]).time_left(&alice, 0), -1);
+    }
+
+    #[test]
+    fn player_out_of_time_is_flagged() {
+        let mut board = timed_game(TimeControl::PerMove { move_secs: 10 });
+        let alice = "alice".to_owned();
+        assert_eq!(board.flagged_player(1_000_000), None);
+        board.perform_move(3, alice.clone());
+        board.press_clock(&alice, 1_000);
+        assert_eq!(board.flagged_player(10_999), None);
+        assert_eq!(board.flagged_player(11_000), Some("bob".to_owned()));
+    }
+
+    #[test]
+    fn computer_is_never_flagged() {
+        let mut board = Board::new(7, 6, "alice".to_owned(), "*".to_owned(), vec![false; 4], 1);
+        board.time_control = Some(TimeControl::PerMove { move_secs: 10 });
+        board.start_clocks();
+        board.perform_move(3, "alice".to_owned());
+        board.press_clock(&"alice".to_owned(), 1_000);
+        assert_eq!(board.flagged_player(100_000), None);
+    }
+
+    #[test]
+    fn take_back_restores_the_earlier_position() {
+        let before = game(&[3, 3]);
+        let mut board = game(&[3, 3, 4, 2]);
+        assert!(board.take_back(2));
+        assert_eq!(board.board, before.board);
+        assert_eq!(board.moves.len(), 2);
+        assert_eq!((board.last_row, board.last_col), (4, 3));
+        assert_eq!(board.get_next_player(), "alice");
+    }
+
+    #[test]
+    fn take_back_of_every_move_empties_the_board() {
+        let mut board = game(&[0, 1]);
+        assert!(board.take_back(2));
+        assert_eq!(board.move_count(), 0);
+        assert_eq!((board.last_row, board.last_col), (-1, -1));
+        assert_eq!(board.get_next_player(), "alice");
+    }
+
+    #[test]
+    fn take_back_of_too_many_moves_is_refused() {
+        let mut board = game(&[0, 1]);
+        assert!(!board.take_back(3));
+        assert!(!board.take_back(0));
+        assert_eq!(board.move_count(), 2);
+    }
+
+    #[test]
+    fn takeback_len_reaches_the_last_move_of_the_player() {
+        let board = game(&[0, 1, 2]);
+        assert_eq!(board.takeback_len(&"alice".to_owned()), 1);
+        assert_eq!(board.takeback_len(&"bob".to_owned()), 2);
+        assert_eq!(game(&[]).takeback_len(&"alice".to_owned()), 0);
+    }
+
+    #[test]
+    fn position_string_round_trips() {
+        assert_eq!(game(&[]).to_position_string(), "7x6 TTTT 7/7/7/7/7/7 x");
+        let played = game(&[3, 3, 4]);
+        assert_eq!(played.to_position_string(), "7x6 TTTT 7/7/7/7/3o3/3xx2 o");
+
+        let mut board = game(&[]);
+        board.set_position(&played.to_position_string()).unwrap();
+        assert_eq!(board.board, played.board);
+        assert_eq!(board.get_next_player(), "bob");
+
+        board.set_position("5x4 OTTO 5/5/5/5 x").unwrap();
+        assert_eq!((board.width, board.height), (5, 4));
+        assert_eq!(board.mode, vec![true, false, false, true]);
+    }
+
+    #[test]
+    fn malformed_position_is_rejected() {
+        let malformed = [
+            "",
+            "7x6 TTTT 7/7/7/7/7/7",
+            "7x6 TTTT 7/7/7/7/7/7 x extra",
+            "7by6 TTTT 7/7/7/7/7/7 x",
+            "3x6 TTTT 3/3/3/3/3/3 x",
+            "21x6 TTTT 21/21/21/21/21/21 x",
+            "7x6 TTT 7/7/7/7/7/7 x",
+            "7x6 TTXT 7/7/7/7/7/7 x",
+            "7x6 TTTT 7/7/7/7/7 x",
+            "7x6 TTTT 7/7/7/7/7/8 x",
+            "7x6 TTTT 7/7/7/7/7/6 x",
+            "7x6 TTTT 7/7/7/7/7/99999999999999999999 x",
+            "7x6 TTTT 7/7/7/7/7/7xo x",
+            "7x6 TTTT 7/7/7/7/7/3z3 x",
+            "7x6 TTTT 7/7/7/7/3x3/7 o",
+            "7x6 TTTT 7/7/7/7/7/3x3 x",
+            "7x6 TTTT 7/7/7/7/7/3x3 z",
+            "7x6 TTTT 7/7/x6/x6/x6/xooo3 o",
+        ];
+        for position in malformed {
+            let mut board = game(&[3]);
+            assert!(board.set_position(position).is_err(), "{}", position);
+            assert_eq!(board.board, game(&[3]).board);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use serde::{Serialize, Deserialize};
+
+use super::general_model::GeneralStatus;
+
+pub const MAX_CHAT_LEN: usize = 200;
+pub const CHAT_COOLDOWN_MS: i64 = 1000;  // How long a sender has to wait between two messages.
+pub const CHAT_REPEAT_MS: i64 = 30 * 1000;  // How long the same message may not be sent again.
+
+// a chat message of a game, kept as a chat event in the event log of the board
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatMessage {
+    pub sender: String,
+    pub text: String,
+    pub date: i64,  // Milliseconds since epoch.
+    pub spectator: bool,
+}
+
+// request model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SendChatRequest {
+    pub name: String,
+    pub pwd: String,
+    pub text: String,
+}
+
+// response model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SendChatResponse {
+    pub status: GeneralStatus,
+    pub message: Option<ChatMessage>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GetChatResponse {
+    pub status: GeneralStatus,
+    pub chat: Vec<ChatMessage>,
+}
@@ -0,0 +1,59 @@
+use serde::{Serialize, Deserialize};
+
+
+// what the reaper does with a game nobody is playing anymore
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReaperAction {
+    Forfeit,  // The player to move loses.
+    Archive,  // The game goes into history without a winner.
+}
+
+// extra settings read from Rocket.toml next to the rocket ones
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AppConfig {
+    #[serde(default = "default_ws_port")]
+    pub ws_port: u16,
+    #[serde(default = "default_abandon_timeout_secs")]
+    pub abandon_timeout_secs: u64,
+    #[serde(default = "default_reaper_interval_secs")]
+    pub reaper_interval_secs: u64,
+    #[serde(default = "default_reaper_action")]
+    pub reaper_action: ReaperAction,
+    #[serde(default)]
+    pub spectator_chat: bool,  // Whether spectators may write in the chat of a game.
+    #[serde(default = "default_search_threads")]
+    pub search_threads: usize,  // Threads one engine search may use, 1 searches on the calling thread.
+    #[serde(default = "default_engine_workers")]
+    pub engine_workers: usize,  // Computer replies worked out at the same time.
+    #[serde(default = "default_engine_timeout_ms")]
+    pub engine_timeout_ms: u64,  // Time a computer reply may search before a quicker search decides.
+}
+
+fn default_ws_port() -> u16 {
+    8001
+}
+
+fn default_abandon_timeout_secs() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_reaper_interval_secs() -> u64 {
+    5 * 60
+}
+
+fn default_reaper_action() -> ReaperAction {
+    ReaperAction::Forfeit
+}
+
+fn default_search_threads() -> usize {
+    1
+}
+
+fn default_engine_workers() -> usize {
+    2
+}
+
+fn default_engine_timeout_ms() -> u64 {
+    10 * 1000
+}
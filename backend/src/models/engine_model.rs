@@ -0,0 +1,80 @@
+use serde::{Serialize, Deserialize};
+use mongodb::bson::oid::ObjectId;
+use chrono::prelude::*;
+
+use super::general_model::GeneralStatus;
+
+// where an engine job is at
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Done,
+    TimedOut,  // The search ran out of time, a quicker search chose the move instead.
+    Cancelled,  // The game moved on or ended before the reply was played.
+    Failed,
+}
+
+impl JobState {
+
+    // whether the job is still waiting for or taking up a worker
+    pub fn is_pending(&self) -> bool {
+        match self {
+            JobState::Queued | JobState::Running => true,
+            JobState::Done | JobState::TimedOut | JobState::Cancelled | JobState::Failed => false,
+        }
+    }
+}
+
+// a computer reply being worked out off the request thread
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EngineJob {
+    pub id: String,
+    pub board_id: String,
+    pub state: JobState,
+    pub cmput_row: i64,
+    pub cmput_col: i64,
+    pub score: i64,  // Positive when player 1 is winning.
+    pub winner: String,  // Set when the reply ended the game.
+    pub created_ms: i64,
+    pub finished_ms: i64,  // 0 while pending.
+}
+
+impl EngineJob {
+
+    pub fn empty() -> Self {
+        EngineJob {
+            id: "".to_owned(),
+            board_id: "".to_owned(),
+            state: JobState::Failed,
+            cmput_row: -1,
+            cmput_col: -1,
+            score: 0,
+            winner: "".to_owned(),
+            created_ms: 0,
+            finished_ms: 0,
+        }
+    }
+
+    pub fn new(board_id: String) -> Self {
+        EngineJob {
+            id: ObjectId::new().to_hex(),
+            board_id,
+            state: JobState::Queued,
+            cmput_row: -1,
+            cmput_col: -1,
+            score: 0,
+            winner: "".to_owned(),
+            created_ms: Utc::now().timestamp_millis(),
+            finished_ms: 0,
+        }
+    }
+}
+
+// response model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EngineJobResponse {
+    pub status: GeneralStatus,
+    pub job: EngineJob,
+}
@@ -0,0 +1,106 @@
+use serde::{Serialize, Deserialize};
+
+use super::board_model::EndReason;
+
+
+// events pushed to everyone watching a game
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GameEvent {
+    MoveMade {
+        player: String,
+        row: i64,
+        col: i64,
+    },
+    Resigned {
+        player: String,
+    },
+    GameOver {
+        winner: String,
+        reason: EndReason,
+    },
+    OpponentJoined {
+        player: String,
+    },
+    Chat {
+        sender: String,
+        text: String,
+        #[serde(default)]
+        date: i64,  // Milliseconds since epoch.
+        #[serde(default)]
+        spectator: bool,
+    },
+    GameStarted {
+        board_id: String,
+        player_1: String,
+        player_2: String,
+    },
+    InviteReceived {
+        invite_id: String,
+        from: String,
+    },
+    InviteDeclined {
+        invite_id: String,
+        by: String,
+    },
+    SpectatorsChanged {
+        count: i64,
+    },
+    TakebackRequested {
+        player: String,
+    },
+    TakebackDeclined {
+        player: String,
+    },
+    TakenBack {
+        moves: i64,
+        next_player: String,
+    },
+}
+
+impl GameEvent {
+
+    // event name used by the event stream, same as the json tag
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameEvent::MoveMade { .. } => "move_made",
+            GameEvent::Resigned { .. } => "resigned",
+            GameEvent::GameOver { .. } => "game_over",
+            GameEvent::OpponentJoined { .. } => "opponent_joined",
+            GameEvent::Chat { .. } => "chat",
+            GameEvent::GameStarted { .. } => "game_started",
+            GameEvent::InviteReceived { .. } => "invite_received",
+            GameEvent::InviteDeclined { .. } => "invite_declined",
+            GameEvent::SpectatorsChanged { .. } => "spectators_changed",
+            GameEvent::TakebackRequested { .. } => "takeback_requested",
+            GameEvent::TakebackDeclined { .. } => "takeback_declined",
+            GameEvent::TakenBack { .. } => "taken_back",
+        }
+    }
+
+    // private events only go to the two players and are never shown to spectators
+    pub fn is_private(&self) -> bool {
+        match self {
+            GameEvent::MoveMade { .. }
+            | GameEvent::Resigned { .. }
+            | GameEvent::GameOver { .. }
+            | GameEvent::OpponentJoined { .. }
+            | GameEvent::Chat { .. }
+            | GameEvent::GameStarted { .. }
+            | GameEvent::InviteReceived { .. }
+            | GameEvent::InviteDeclined { .. }
+            | GameEvent::SpectatorsChanged { .. }
+            | GameEvent::TakenBack { .. } => false,
+            GameEvent::TakebackRequested { .. }
+            | GameEvent::TakebackDeclined { .. } => true,
+        }
+    }
+}
+
+// an event together with its place in the event sequence of the game, 0 for events that are not logged
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GameEventEntry {
+    pub seq: i64,
+    #[serde(flatten)]
+    pub event: GameEvent,
+}
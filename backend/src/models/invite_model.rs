@@ -0,0 +1,162 @@
+use serde::{Serialize, Deserialize};
+use mongodb::bson::oid::ObjectId;
+use chrono::prelude::*;
+
+use super::general_model::GeneralStatus;
+use super::lobby_model::GameSettings;
+
+// how long an invitation can be answered
+pub const INVITE_TTL_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum InviteStatus {
+    Pending,
+    Accepted,
+    Declined,
+    Expired,
+}
+
+impl InviteStatus {
+
+    // the value stored in mongodb
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InviteStatus::Pending => "pending",
+            InviteStatus::Accepted => "accepted",
+            InviteStatus::Declined => "declined",
+            InviteStatus::Expired => "expired",
+        }
+    }
+}
+
+// an invitation from one user to another to play a game on the given settings
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Invitation {
+    pub id: String,
+    pub from: String,
+    pub to: String,
+    pub settings: GameSettings,
+    pub status: InviteStatus,
+    pub date: String,
+    pub expires_at: i64,  // Unix timestamp in seconds.
+    pub board_id: String,  // The board of the game once accepted, empty before.
+}
+
+impl Invitation {
+
+    pub fn empty() -> Self {
+        Invitation {
+            id: "".to_owned(),
+            from: "".to_owned(),
+            to: "".to_owned(),
+            settings: GameSettings::empty(),
+            status: InviteStatus::Expired,
+            date: "".to_owned(),
+            expires_at: 0,
+            board_id: "".to_owned(),
+        }
+    }
+
+    pub fn new(from: String, to: String, settings: GameSettings) -> Self {
+        Invitation {
+            id: ObjectId::new().to_hex(),
+            from,
+            to,
+            settings,
+            status: InviteStatus::Pending,
+            date: Local::now().to_string(),
+            expires_at: Utc::now().timestamp() + INVITE_TTL_SECS,
+            board_id: "".to_owned(),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.status == InviteStatus::Expired
+            || (self.status == InviteStatus::Pending && Utc::now().timestamp() > self.expires_at)
+    }
+
+    // check that the user can still accept or decline, only the invited user can and only once
+    pub fn answerable_by(&self, user: &str) -> Result<(), &'static str> {
+
+        if !self.to.eq(user) {
+            return Err("Invitation does not exist.");
+        }
+        if self.is_expired() {
+            return Err("Invitation has expired.");
+        }
+        match self.status {
+            InviteStatus::Pending => Ok(()),
+            _ => Err("Invitation was already answered."),
+        }
+    }
+}
+
+// request model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SendInviteRequest {
+    pub name: String,
+    pub pwd: String,
+    pub to: String,
+    pub settings: GameSettings,
+}
+
+// response model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GeneralInviteResponse {
+    pub status: GeneralStatus,
+    pub invite: Invitation,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GetInvitesResponse {
+    pub status: GeneralStatus,
+    pub incoming: Vec<Invitation>,
+    pub outgoing: Vec<Invitation>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invite() -> Invitation {
+        Invitation::new(
+            "alice".to_owned(),
+            "bob".to_owned(),
+            GameSettings { width: 7, height: 6, mode: vec![false; 4], time_control: None }
+        )
+    }
+
+    #[test]
+    fn only_the_invited_user_can_answer() {
+        let invite = invite();
+        assert_eq!(invite.answerable_by("bob"), Ok(()));
+        assert_eq!(invite.answerable_by("alice"), Err("Invitation does not exist."));
+        assert_eq!(invite.answerable_by("carol"), Err("Invitation does not exist."));
+    }
+
+    #[test]
+    fn answered_invitation_cannot_be_answered_again() {
+        let mut invite = invite();
+        invite.status = InviteStatus::Accepted;
+        assert_eq!(invite.answerable_by("bob"), Err("Invitation was already answered."));
+        invite.status = InviteStatus::Declined;
+        assert_eq!(invite.answerable_by("bob"), Err("Invitation was already answered."));
+    }
+
+    #[test]
+    fn invitation_past_its_deadline_has_expired() {
+        let mut invite = invite();
+        invite.expires_at = Utc::now().timestamp() - 1;
+        assert!(invite.is_expired());
+        assert_eq!(invite.answerable_by("bob"), Err("Invitation has expired."));
+    }
+
+    #[test]
+    fn accepted_invitation_is_played_by_the_inviting_user_first() {
+        let invite = invite();
+        let board = invite.settings.new_board(&invite.from, &invite.to);
+        assert_eq!((board.player_1.as_str(), board.player_2.as_str()), ("alice", "bob"));
+        assert_eq!((board.width, board.height), (7, 6));
+    }
+}
@@ -0,0 +1,147 @@
+use serde::{Serialize, Deserialize};
+use mongodb::bson::oid::ObjectId;
+use chrono::prelude::*;
+
+use super::board_model::{Board, TimeControl};
+use super::general_model::GeneralStatus;
+
+// games between two humans are always played on difficulty 1
+pub const HUMAN_DIFFICULTY: i64 = 1;
+
+// the settings two players agree on before a game
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GameSettings {
+    pub width: i64,
+    pub height: i64,
+    pub mode: Vec<bool>,
+    #[serde(default)]
+    pub time_control: Option<TimeControl>,
+}
+
+impl GameSettings {
+
+    pub fn empty() -> Self {
+        GameSettings {
+            width: 0,
+            height: 0,
+            mode: vec![],
+            time_control: None,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        Board::valid_size(self.width, self.height) && self.mode.len() == 4
+    }
+
+    /*
+    Create a fresh board for a game between two humans on these settings.
+     */
+    pub fn new_board(&self, p1: &str, p2: &str) -> Board {
+        let mut board = Board::new(
+            self.width,
+            self.height,
+            p1.to_owned(),
+            p2.to_owned(),
+            self.mode.clone(),
+            HUMAN_DIFFICULTY
+        );
+        board.time_control = self.time_control.clone();
+        board
+    }
+}
+
+// an open challenge in the lobby, anyone can accept it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Challenge {
+    pub id: String,
+    pub creator: String,
+    pub rating: i64,
+    pub settings: GameSettings,
+    pub date: String,
+}
+
+impl Challenge {
+
+    pub fn empty() -> Self {
+        Challenge {
+            id: "".to_owned(),
+            creator: "".to_owned(),
+            rating: 0,
+            settings: GameSettings::empty(),
+            date: "".to_owned(),
+        }
+    }
+
+    pub fn new(creator: String, rating: i64, settings: GameSettings) -> Self {
+        Challenge {
+            id: ObjectId::new().to_hex(),
+            creator,
+            rating,
+            settings,
+            date: Local::now().to_string(),
+        }
+    }
+}
+
+// a player waiting in the auto-match queue
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QueueEntry {
+    pub name: String,
+    pub rating: i64,
+    pub rating_range: i64,
+    pub settings: GameSettings,
+}
+
+impl QueueEntry {
+
+    // both players must be within the rating range of each other
+    pub fn matches(&self, other: &QueueEntry) -> bool {
+        let diff = (self.rating - other.rating).abs();
+        self.name != other.name
+            && self.settings == other.settings
+            && diff <= self.rating_range
+            && diff <= other.rating_range
+    }
+}
+
+// request model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LobbyLoginRequest {
+    pub name: String,
+    pub pwd: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PostChallengeRequest {
+    pub name: String,
+    pub pwd: String,
+    pub settings: GameSettings,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JoinQueueRequest {
+    pub name: String,
+    pub pwd: String,
+    pub settings: GameSettings,
+    pub rating_range: i64,
+}
+
+// response model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GeneralChallengeResponse {
+    pub status: GeneralStatus,
+    pub challenge: Challenge,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GetAllChallengeResponse {
+    pub status: GeneralStatus,
+    pub all_challenges: Vec<Challenge>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JoinQueueResponse {
+    pub status: GeneralStatus,
+    pub matched: bool,
+    pub board: Board,
+}
@@ -1,3 +1,11 @@
 pub mod general_model;
 pub mod board_model;
 pub mod user_model;
+pub mod event_model;
+pub mod config_model;
+pub mod lobby_model;
+pub mod invite_model;
+pub mod chat_model;
+pub mod notation_model;
+pub mod analysis_model;
+pub mod engine_model;
@@ -0,0 +1,453 @@
+use serde::{Serialize, Deserialize};
+use chrono::prelude::*;
+
+use super::board_model::{Board, EndReason, HistBoard, MAX_SIZE, MIN_SIZE};
+use super::general_model::GeneralStatus;
+
+const DRAW_STR: &str = "^";
+
+/*
+A whole game written down as text, much like PGN for chess:
+
+[Player1 "alice"]
+[Player2 "*"]
+[Size "7x6"]
+[Mode "TTTT"]
+[WinLength "4"]
+[Difficulty "3"]
+[Date "2023-04-01 12:00:00"]
+[Result "1-0"]
+[Reason "connected"]
+
+1. 4 4 2. 5 3 3. 6 2 4. 7 1-0
+
+Columns are counted from 1. The result is 1-0 when player 1 won, 0-1 when player 2 won,
+1/2-1/2 for a draw and * when there is no winner. Games that did not start on an empty board
+carry their starting position string in a Position header.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GameNotation {
+    pub player_1: String,
+    pub player_2: String,
+    pub width: i64,
+    pub height: i64,
+    pub mode: Vec<bool>,
+    pub difficulty: i64,
+    pub date: String,
+    pub winner: String,  // Name of the winner, ^ for a draw, empty without a winner.
+    pub reason: Option<EndReason>,
+    pub moves: Vec<i64>,  // Columns counted from 0 like everywhere else.
+    pub start_position: String,  // Empty when the game started on an empty board.
+}
+
+// write a header value in quotes
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// read a header line like [Key "value"]
+fn parse_header(line: &str) -> Result<(String, String), String> {
+
+    let inner = match line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+        Some(i) => i.trim(),
+        None => return Err(format!("Invalid header line: {}", line)),
+    };
+    let (key, value) = match inner.split_once(' ') {
+        Some((k, v)) => (k.trim(), v.trim()),
+        None => return Err(format!("Invalid header line: {}", line)),
+    };
+    let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(v) => v,
+        None => return Err(format!("Header value must be quoted: {}", line)),
+    };
+
+    let mut unquoted = String::new();
+    let mut escaped = false;
+    for c in value.chars() {
+        match (escaped, c) {
+            (false, '\\') => escaped = true,
+            _ => {
+                unquoted.push(c);
+                escaped = false;
+            },
+        }
+    }
+    Ok((key.to_owned(), unquoted))
+}
+
+fn parse_number(key: &str, value: &str) -> Result<i64, String> {
+    match value.parse::<i64>() {
+        Ok(n) => Ok(n),
+        Err(_) => Err(format!("{} must be a number.", key)),
+    }
+}
+
+impl GameNotation {
+
+    pub fn from_hist(hist: &HistBoard) -> Self {
+        GameNotation {
+            player_1: hist.board.player_1.clone(),
+            player_2: hist.board.player_2.clone(),
+            width: hist.board.width,
+            height: hist.board.height,
+            mode: hist.board.mode.clone(),
+            difficulty: hist.board.difficulty,
+            date: hist.date.clone(),
+            winner: hist.winner.clone(),
+            reason: hist.reason.clone(),
+            moves: hist.board.moves.iter().map(|m| m.col).collect(),
+            start_position: hist.board.start_position.clone(),
+        }
+    }
+
+    // write down a game from its board, which may still be going on
+    pub fn from_board(board: &Board, winner: String, reason: Option<EndReason>) -> Self {
+        GameNotation {
+            player_1: board.player_1.clone(),
+            player_2: board.player_2.clone(),
+            width: board.width,
+            height: board.height,
+            mode: board.mode.clone(),
+            difficulty: board.difficulty,
+            date: Local::now().to_string(),
+            winner,
+            reason,
+            moves: board.moves.iter().map(|m| m.col).collect(),
+            start_position: board.start_position.clone(),
+        }
+    }
+
+    fn result(&self) -> &'static str {
+        if self.winner == DRAW_STR {
+            "1/2-1/2"
+        } else if !self.winner.is_empty() && self.winner == self.player_1 {
+            "1-0"
+        } else if !self.winner.is_empty() && self.winner == self.player_2 {
+            "0-1"
+        } else { "*" }
+    }
+
+    /*
+    Write the game down, two moves per numbered line like PGN does.
+     */
+    pub fn to_text(&self) -> String {
+
+        let mode: String = self.mode.iter().map(|o| if *o { 'O' } else { 'T' }).collect();
+        let mut text = vec![
+            format!("[Player1 {}]", quote(&self.player_1)),
+            format!("[Player2 {}]", quote(&self.player_2)),
+            format!("[Size \"{}x{}\"]", self.width, self.height),
+            format!("[Mode \"{}\"]", mode),
+            format!("[WinLength \"{}\"]", self.mode.len()),
+            format!("[Difficulty \"{}\"]", self.difficulty),
+            format!("[Date {}]", quote(&self.date)),
+            format!("[Result \"{}\"]", self.result()),
+        ];
+        if let Some(r) = &self.reason {
+            text.push(format!("[Reason \"{}\"]", r.as_str()));
+        }
+        if !self.start_position.is_empty() {
+            text.push(format!("[Position {}]", quote(&self.start_position)));
+        }
+        text.push("".to_owned());
+
+        let mut moves: Vec<String> = vec![];
+        for (i, col) in self.moves.iter().enumerate() {
+            if i % 2 == 0 {
+                moves.push(format!("{}.", i / 2 + 1));
+            }
+            moves.push((col + 1).to_string());
+        }
+        moves.push(self.result().to_owned());
+        text.push(moves.join(" "));
+        text.join("\n") + "\n"
+    }
+
+    /*
+    Read a game written down by to_text.
+    Only the format is checked here, use replay to check the moves against the rules.
+     */
+    pub fn parse(text: &str) -> Result<Self, String> {
+
+        let mut notation = GameNotation {
+            player_1: "".to_owned(),
+            player_2: "".to_owned(),
+            width: 0,
+            height: 0,
+            mode: vec![],
+            difficulty: 1,
+            date: "".to_owned(),
+            winner: "".to_owned(),
+            reason: None,
+            moves: vec![],
+            start_position: "".to_owned(),
+        };
+        let (mut result, mut win_length) = ("*".to_owned(), 4);
+
+        let mut movetext: Vec<&str> = vec![];
+        for line in text.lines().map(|l| l.trim()) {
+            if !line.starts_with('[') {
+                movetext.push(line);
+                continue;
+            }
+            let (key, value) = parse_header(line)?;
+            match key.as_str() {
+                "Player1" => notation.player_1 = value,
+                "Player2" => notation.player_2 = value,
+                "Size" => {
+                    let (w, h) = match value.split_once('x') {
+                        Some(wh) => wh,
+                        None => return Err("Size must look like 7x6.".to_owned()),
+                    };
+                    notation.width = parse_number("Size", w)?;
+                    notation.height = parse_number("Size", h)?;
+                },
+                "Mode" => {
+                    if !value.chars().all(|c| c == 'T' || c == 'O') {
+                        return Err("Mode may only contain T and O.".to_owned());
+                    }
+                    notation.mode = value.chars().map(|c| c == 'O').collect();
+                },
+                "WinLength" => win_length = parse_number("WinLength", &value)?,
+                "Difficulty" => notation.difficulty = parse_number("Difficulty", &value)?,
+                "Date" => notation.date = value,
+                "Result" => result = value,
+                "Position" => notation.start_position = value,
+                "Reason" => notation.reason = match EndReason::parse(&value) {
+                    Some(r) => Some(r),
+                    None => return Err(format!("Unknown reason: {}", value)),
+                },
+                // unknown headers are kept by other tools, we just skip them
+                _ => (),
+            };
+        }
+
+        if notation.player_1.is_empty() || notation.player_2.is_empty() || notation.player_1 == notation.player_2 {
+            return Err("Player1 and Player2 must be two different players.".to_owned());
+        }
+        if !Board::valid_size(notation.width, notation.height) {
+            return Err(format!("The board must be between {0}x{0} and {1}x{1}.", MIN_SIZE, MAX_SIZE));
+        }
+        if notation.mode.len() != 4 || win_length != 4 {
+            return Err("Only games with a win length of 4 are supported.".to_owned());
+        }
+
+        let mut result_seen = false;
+        for token in movetext.join(" ").split_whitespace() {
+            if result_seen {
+                return Err(format!("Unexpected text after the result: {}", token));
+            }
+            match token {
+                "1-0" | "0-1" | "1/2-1/2" | "*" => {
+                    if token != result {
+                        return Err("The result at the end of the moves does not match the Result header.".to_owned());
+                    }
+                    result_seen = true;
+                },
+                t if t.ends_with('.') => (),
+                t => match t.parse::<i64>() {
+                    Ok(col) if 1 <= col && col <= notation.width => notation.moves.push(col - 1),
+                    _ => return Err(format!("Invalid move: {}", t)),
+                },
+            };
+        }
+
+        notation.winner = match result.as_str() {
+            "1-0" => notation.player_1.clone(),
+            "0-1" => notation.player_2.clone(),
+            "1/2-1/2" => DRAW_STR.to_owned(),
+            "*" => "".to_owned(),
+            r => return Err(format!("Unknown result: {}", r)),
+        };
+        Ok(notation)
+    }
+
+    // play the moves on a fresh board, every move has to fit and the game must not be over before it
+    fn play_moves(&self) -> Result<Board, String> {
+
+        let mut board = Board::new(self.width, self.height, self.player_1.clone(), self.player_2.clone(), self.mode.clone(), self.difficulty);
+        if !self.start_position.is_empty() {
+            board.set_position(&self.start_position)?;
+            if board.width != self.width || board.height != self.height || board.mode != self.mode {
+                return Err("The position does not match the size and mode.".to_owned());
+            }
+        }
+        for (i, col) in self.moves.iter().enumerate() {
+            if board.is_terminal() {
+                return Err(format!("Move {} is played after the game is over.", i + 1));
+            }
+            if !board.allows_move(col) {
+                return Err(format!("Move {} is played into a full column.", i + 1));
+            }
+            let next_player = board.get_next_player();
+            board.record_move(*col, next_player, None);
+        }
+        Ok(board)
+    }
+
+    /*
+    Play the moves on a fresh board to make sure the game follows the rules:
+    every move has to fit into its column, nobody may move once the game is over,
+    and a game that ended on the board has to end with the result it claims.
+     */
+    pub fn replay(&self) -> Result<Board, String> {
+
+        let mut board = self.play_moves()?;
+        let (connected, winner) = board._has_winner();
+        let (winner, reason) = if connected {
+            (winner, EndReason::Connected)
+        } else if board.is_draw() {
+            (DRAW_STR.to_owned(), EndReason::Draw)
+        } else {
+            // the game ended before the board did, a winner without a reason has resigned
+            match (&self.reason, self.winner.as_str()) {
+                (Some(EndReason::Connected), _) | (Some(EndReason::Draw), _) | (_, DRAW_STR) => {
+                    return Err("The result does not match the final position.".to_owned());
+                },
+                (Some(r), _) => (self.winner.clone(), r.clone()),
+                (None, "") => return Err("The game has no result.".to_owned()),
+                (None, _) => (self.winner.clone(), EndReason::Resigned),
+            }
+        };
+
+        if winner != self.winner {
+            return Err("The result does not match the final position.".to_owned());
+        }
+        match &self.reason {
+            Some(r) if *r != reason => return Err("The reason does not match the final position.".to_owned()),
+            _ => (),
+        };
+        board.end_reason = Some(reason);
+        Ok(board)
+    }
+
+    /*
+    Set up the board of a game that is not over yet, so that it can be played on.
+     */
+    pub fn resume(&self) -> Result<Board, String> {
+
+        let board = self.play_moves()?;
+        if !self.winner.is_empty() || self.reason.is_some() || board.is_terminal() {
+            return Err("The game is already over.".to_owned());
+        }
+        Ok(board)
+    }
+
+    /*
+    Turn the game into a history record, checking it against the rules first.
+    Only games with a result go into history, an unfinished or archived one has none.
+     */
+    pub fn to_hist(&self) -> Result<HistBoard, String> {
+
+        if self.winner.is_empty() {
+            return Err("The game has no result.".to_owned());
+        }
+        let board = self.replay()?;
+        let mut hist = HistBoard::new(board, self.winner.clone());
+        if !self.date.is_empty() {
+            hist.date = self.date.clone();
+        }
+        Ok(hist)
+    }
+}
+
+// request model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportGameRequest {
+    pub name: String,
+    pub pwd: String,
+    pub notation: String,
+}
+
+// response model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportGameResponse {
+    pub status: GeneralStatus,
+    pub notation: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // alice stacks four checkers in the first column
+    fn won_game() -> Board {
+        let mut board = Board::new(7, 6, "alice".to_owned(), "b\"ob".to_owned(), vec![false; 4], 3);
+        for col in [0, 1, 0, 1, 0, 1, 0] {
+            let player = board.get_next_player();
+            board.record_move(col, player, None);
+        }
+        board
+    }
+
+    fn notation(winner: &str, reason: Option<EndReason>) -> GameNotation {
+        let mut hist = HistBoard::new(won_game(), winner.to_owned());
+        hist.reason = reason;
+        GameNotation::from_hist(&hist)
+    }
+
+    #[test]
+    fn exported_game_parses_back() {
+        let notation = notation("alice", Some(EndReason::Connected));
+        let text = notation.to_text();
+        assert!(text.contains("1. 1 2 2. 1 2 3. 1 2 4. 1 1-0"));
+
+        let parsed = GameNotation::parse(&text).unwrap();
+        assert_eq!(parsed.player_1, "alice");
+        assert_eq!(parsed.player_2, "b\"ob");
+        assert_eq!((parsed.width, parsed.height, parsed.difficulty), (7, 6, 3));
+        assert_eq!(parsed.moves, notation.moves);
+        assert_eq!(parsed.winner, "alice");
+        assert_eq!(parsed.reason, Some(EndReason::Connected));
+        assert_eq!(parsed.date, notation.date);
+        assert_eq!(parsed.to_text(), text);
+        assert_eq!(parsed.replay().unwrap().board, won_game().board);
+    }
+
+    #[test]
+    fn game_from_a_position_keeps_it() {
+        let mut board = Board::new(7, 6, "alice".to_owned(), "bob".to_owned(), vec![false; 4], 1);
+        board.set_position("7x6 TTTT 7/7/7/7/7/3xo2 x").unwrap();
+        let notation = GameNotation::from_board(&board, "".to_owned(), None);
+        let parsed = GameNotation::parse(&notation.to_text()).unwrap();
+        assert_eq!(parsed.start_position, "7x6 TTTT 7/7/7/7/7/3xo2 x");
+        assert_eq!(parsed.resume().unwrap().board, board.board);
+    }
+
+    #[test]
+    fn malformed_notation_is_rejected() {
+        let text = notation("alice", None).to_text();
+        assert!(GameNotation::parse(&text.replace("[Size \"7x6\"]", "[Size \"7x3\"]")).is_err());
+        assert!(GameNotation::parse(&text.replace("[Size \"7x6\"]", "[Size \"7x99\"]")).is_err());
+        assert!(GameNotation::parse(&text.replace("[Size \"7x6\"]", "[Size 7x6]")).is_err());
+        assert!(GameNotation::parse(&text.replace("[Mode \"TTTT\"]", "[Mode \"TTXT\"]")).is_err());
+        assert!(GameNotation::parse(&text.replace("4. 1 1-0", "4. 8 1-0")).is_err());
+        assert!(GameNotation::parse(&text.replace("4. 1 1-0", "4. 1 0-1")).is_err());
+        assert!(GameNotation::parse(&text.replace("1-0\n", "1-0 5\n")).is_err());
+        assert!(GameNotation::parse(&text.replace("b\\\"ob", "alice")).is_err());
+    }
+
+    #[test]
+    fn replay_checks_the_claimed_result() {
+        let mut notation = notation("b\"ob", None);
+        assert!(notation.replay().is_err());
+        notation.winner = "alice".to_owned();
+        assert_eq!(notation.replay().unwrap().end_reason, Some(EndReason::Connected));
+        notation.moves.push(2);
+        assert!(notation.replay().is_err());
+    }
+
+    #[test]
+    fn game_without_a_result_cannot_be_imported() {
+        let mut board = won_game();
+        assert!(board.take_back(1));
+        let text = GameNotation::from_board(&board, "".to_owned(), None).to_text();
+        assert!(text.contains(" *\n"));
+        let parsed = GameNotation::parse(&text).unwrap();
+        assert!(parsed.replay().is_err());
+        assert!(parsed.to_hist().is_err());
+
+        let archived = GameNotation::from_board(&board, "".to_owned(), Some(EndReason::Archived));
+        assert!(GameNotation::parse(&archived.to_text()).unwrap().to_hist().is_err());
+    }
+}
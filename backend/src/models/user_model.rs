@@ -48,6 +48,8 @@ pub struct GeneralUserResponse {
 pub struct PwdVerifyResponse {
     pub status: GeneralStatus,
     pub exists: bool,
+    #[serde(default)]
+    pub token: String,  // Proves who the user is to the websocket and event streams, empty if the password is wrong.
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
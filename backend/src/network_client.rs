@@ -0,0 +1,341 @@
+use std::{io, net::TcpStream};
+
+use serde::{de::DeserializeOwned, Serialize};
+use rocket::serde::json::from_str;
+use tokio_tungstenite::tungstenite::{connect, stream::MaybeTlsStream, Message, WebSocket};
+
+use crate::models::{
+    board_model::*,
+    event_model::{GameEvent, GameEventEntry},
+    general_model::GeneralStatus,
+    user_model::{PwdVerifyResponse, User},
+};
+
+const COMPUTER_STR: &str = "*";
+const DRAW_STR: &str = "^";
+
+// the events of a board as they come in over the websocket server
+pub struct EventSocket(WebSocket<MaybeTlsStream<TcpStream>>);
+
+impl EventSocket {
+
+    // wait for the next event, None once the server closed the connection after the game
+    pub fn next(&mut self) -> Option<GameEventEntry> {
+        loop {
+            match self.0.read_message() {
+                Ok(Message::Text(text)) => match from_str(&text) {
+                    Ok(entry) => return Some(entry),
+                    Err(_) => continue,
+                },
+                Ok(Message::Close(_)) | Err(_) => return None,
+                Ok(_) => continue,
+            };
+        }
+    }
+}
+
+// escape a name so that it can go into a url
+fn url_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/*
+A client of a running server, talking to the same routes the web app uses.
+The requests and responses are the models of the server itself.
+ */
+pub struct Client {
+    server: String,  // Such as http://127.0.0.1:8000, without a slash at the end.
+    ws: String,  // The websocket server on the same host, such as ws://127.0.0.1:8001.
+}
+
+impl Client {
+
+    pub fn new(server: &str, ws_port: u16) -> Result<Self, String> {
+
+        let server = server.trim_end_matches('/').to_owned();
+        let host = match server.split_once("://") {
+            Some(("http", rest)) | Some(("https", rest)) => rest.split('/').next().unwrap_or(""),
+            _ => return Err(format!("Server '{}' should look like http://127.0.0.1:8000.", server)),
+        };
+        let host = match host.rsplit_once(':') {
+            Some((h, _)) => h,
+            None => host,
+        };
+        Ok(Client {
+            ws: format!("ws://{}:{}", host, ws_port),
+            server,
+        })
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, String> {
+        ureq::get(&format!("{}{}", self.server, path))
+            .call()
+            .map_err(|e| format!("Request to the server failed: {}", e))?
+            .into_json()
+            .map_err(|e| format!("Unexpected answer from the server: {}", e))
+    }
+
+    fn post<T: DeserializeOwned, B: Serialize>(&self, path: &str, body: &B) -> Result<T, String> {
+        ureq::post(&format!("{}{}", self.server, path))
+            .send_json(body)
+            .map_err(|e| format!("Request to the server failed: {}", e))?
+            .into_json()
+            .map_err(|e| format!("Unexpected answer from the server: {}", e))
+    }
+
+    // turn a failed status into the message of the server
+    fn check(status: &GeneralStatus) -> Result<(), String> {
+        match status.success {
+            true => Ok(()),
+            false => Err(status.msg.clone()),
+        }
+    }
+
+    // log in and get the token that makes the event streams treat us as the user
+    pub fn login(&self, name: &str, pwd: &str) -> Result<String, String> {
+
+        let user = User { name: name.to_owned(), pwd: pwd.to_owned(), score: 0 };
+        let res: PwdVerifyResponse = self.post("/user/verify", &user)?;
+        Self::check(&res.status)?;
+        match res.exists {
+            true => Ok(res.token),
+            false => Err("User password combination does not exist.".to_owned()),
+        }
+    }
+
+    pub fn live_games(&self) -> Result<Vec<LiveGame>, String> {
+        let res: GetLiveGamesResponse = self.get("/board/live")?;
+        Self::check(&res.status)?;
+        Ok(res.live_games)
+    }
+
+    pub fn create_board(&self, board: &Board, position: String) -> Result<Board, String> {
+        let req = CreateBoardRequest { board: board.clone(), position };
+        let res: GeneralBoardResponse = self.post("/board/create", &req)?;
+        Self::check(&res.status)?;
+        Ok(res.board)
+    }
+
+    // the full board of a running game the user plays on, together with its event log
+    pub fn board(&self, id: &str, name: &str, pwd: &str) -> Result<Board, String> {
+        let req = PlayerRequest { name: name.to_owned(), pwd: pwd.to_owned() };
+        let res: GeneralBoardResponse = self.post(&format!("/board/{}/state", url_encode(id)), &req)?;
+        Self::check(&res.status)?;
+        Ok(res.board)
+    }
+
+    pub fn spectate(&self, id: &str) -> Result<SpectatorView, String> {
+        let res: SpectateResponse = self.get(&format!("/board/{}/spectate", url_encode(id)))?;
+        Self::check(&res.status)?;
+        Ok(res.view)
+    }
+
+    // move in a column as the player the token was given to, -1 gives the game up
+    pub fn play_move(&self, board: &Board, col: i64, token: &str) -> Result<PerformMoveResponse, String> {
+        let req = PerformMoveRequest {
+            board_info: board.clone(),
+            col,
+            token: token.to_owned(),
+        };
+        let res: PerformMoveResponse = self.post("/board/move", &req)?;
+        Self::check(&res.status)?;
+        Ok(res)
+    }
+
+    pub fn takeback(&self, id: &str, name: &str, pwd: &str, action: TakebackAction) -> Result<(), String> {
+        let req = TakebackRequest { name: name.to_owned(), pwd: pwd.to_owned(), action };
+        let res: GeneralBoardResponse = self.post(&format!("/board/{}/takeback", url_encode(id)), &req)?;
+        Self::check(&res.status)
+    }
+
+    // listen to a board, as the player the token was given to or as a spectator when it is empty
+    pub fn events(&self, id: &str, token: &str) -> Result<EventSocket, String> {
+        let url = format!("{}/board/{}/ws?token={}", self.ws, url_encode(id), url_encode(token));
+        match connect(url.as_str()) {
+            Ok((socket, _)) => Ok(EventSocket(socket)),
+            Err(e) => Err(format!("Cannot connect to the websocket server at {}: {}", self.ws, e)),
+        }
+    }
+}
+
+fn display_name(player: &str) -> String {
+    match player {
+        COMPUTER_STR => "Computer".to_owned(),
+        name => name.to_owned(),
+    }
+}
+
+fn print_result(winner: &str, reason: &Option<EndReason>) {
+    let how = match reason {
+        Some(r) => format!(" ({})", r.as_str()),
+        None => "".to_owned(),
+    };
+    match winner {
+        DRAW_STR => println!("The game is a draw{}.", how),
+        w => println!("{} wins{}.", display_name(w), how),
+    };
+}
+
+// say what an event that does not change the board was about, true when the game is over
+fn report(event: &GameEvent) -> bool {
+    match event {
+        GameEvent::GameOver { winner, reason } => {
+            print_result(winner, &Some(reason.clone()));
+            return true;
+        },
+        GameEvent::Resigned { player } => println!("{} resigned.", display_name(player)),
+        GameEvent::OpponentJoined { player } => println!("{} joined the game.", display_name(player)),
+        GameEvent::Chat { sender, text, .. } => println!("[{}] {}", sender, text),
+        GameEvent::SpectatorsChanged { count } => println!("{} watching.", count),
+        GameEvent::TakebackRequested { player } => println!("{} asks to take back their move.", player),
+        GameEvent::TakebackDeclined { player } => println!("{} declined the takeback.", player),
+        _ => (),
+    };
+    false
+}
+
+fn report_takeback(moves: i64) {
+    println!("{} move{} taken back.", moves, if moves == 1 { " was" } else { "s were" });
+}
+
+/*
+Wait for the next event the player may have to act on and apply it to the board,
+printing the other events as they come. Events already in the event log of the board are skipped.
+Returns false once the game is over.
+ */
+fn wait_for_change(board: &mut Board, socket: &mut EventSocket) -> bool {
+    loop {
+        let entry = match socket.next() {
+            Some(e) => e,
+            None => return false,
+        };
+        if entry.seq != 0 && entry.seq <= board.events.len() as i64 {
+            continue;
+        }
+        if entry.seq != 0 {
+            board.events.push(entry.clone());
+        }
+        match entry.event {
+            GameEvent::MoveMade { player, col, .. } => {
+                println!("{} played column {}.", display_name(&player), col);
+                board.record_move(col, player, None);
+                return true;
+            },
+            GameEvent::TakenBack { moves, .. } => {
+                report_takeback(moves);
+                board.take_back(moves);
+                return true;
+            },
+            GameEvent::TakebackRequested { player } => {
+                board.takeback_request = player.clone();
+                report(&GameEvent::TakebackRequested { player });
+                return true;
+            },
+            GameEvent::TakebackDeclined { player } => {
+                board.takeback_request = "".to_owned();
+                report(&GameEvent::TakebackDeclined { player });
+                return true;
+            },
+            event => if report(&event) {
+                return false;
+            },
+        };
+    }
+}
+
+/*
+Play a running game of the server as one of its players, typing moves like the debug loop.
+The board follows the events of the game, so the opponent can play from anywhere.
+ */
+pub fn play(client: &Client, id: &str, name: &str, pwd: &str) -> Result<(), String> {
+
+    let token = client.login(name, pwd)?;
+    // connect first so that no move falls between reading the board and listening
+    let mut socket = client.events(id, &token)?;
+    let mut board = client.board(id, name, pwd)?;
+    println!("{} against {}, you play {}.", display_name(&board.player_1), display_name(&board.player_2), if name.eq(&board.player_1) { "T" } else { "O" });
+    println!("Type a column to move, u to take your move back, a or d to answer a takeback, r to resign and q to leave.");
+
+    loop {
+        println!("{}", board.print());
+        if board.is_terminal() || !name.eq(&board.get_next_player()) {
+            println!("Waiting for {}...", display_name(&board.opponent(&name.to_owned())));
+            match wait_for_change(&mut board, &mut socket) {
+                true => continue,
+                false => return Ok(()),
+            };
+        }
+
+        let mut input = String::new();
+        println!("{}'s choice: ", name);
+        io::stdin().read_line(&mut input).map_err(|e| e.to_string())?;
+        let result = match input.trim() {
+            "q" => return Ok(()),
+            "u" => client.takeback(id, name, pwd, TakebackAction::Request).map(|_| None),
+            "a" => client.takeback(id, name, pwd, TakebackAction::Accept).map(|_| None),
+            // declining leaves the board as it is, so there is nothing to wait for
+            "d" => match client.takeback(id, name, pwd, TakebackAction::Decline) {
+                Ok(()) => continue,
+                Err(msg) => Err(msg),
+            },
+            "r" => client.play_move(&board, -1, &token).map(Some),
+            col => match col.parse::<i64>() {
+                Ok(col) if board.allows_move(&col) => client.play_move(&board, col, &token).map(Some),
+                Ok(_) => Err("Move is not allowed. Please try again.".to_owned()),
+                Err(_) => Err("Invalid input. Please try again.".to_owned()),
+            },
+        };
+        match result {
+            Ok(Some(res)) if !res.winner.is_empty() => {
+                print_result(&res.winner, &res.reason);
+                return Ok(());
+            },
+            // the move or takeback shows up on the event stream
+            Ok(_) => if !wait_for_change(&mut board, &mut socket) {
+                return Ok(());
+            },
+            Err(msg) => println!("{}", msg),
+        };
+    }
+}
+
+/*
+Follow a running game as a spectator, printing the board after every move until the game is over.
+ */
+pub fn watch(client: &Client, id: &str) -> Result<(), String> {
+
+    let mut socket = client.events(id, "")?;
+    let view = client.spectate(id)?;
+    println!("{} against {}, {} watching.", display_name(&view.player_1), display_name(&view.player_2), view.spectators);
+    let mut board = Board::new(view.width, view.height, view.player_1.clone(), view.player_2.clone(), view.mode.clone(), 1);
+    board.board = view.board;
+    println!("{}", board.print());
+
+    loop {
+        let entry = match socket.next() {
+            Some(e) => e,
+            None => return Ok(()),
+        };
+        match &entry.event {
+            GameEvent::MoveMade { player, col, .. } => println!("{} played column {}.", display_name(player), col),
+            GameEvent::TakenBack { moves, .. } => report_takeback(*moves),
+            event => {
+                if report(event) {
+                    return Ok(());
+                }
+                continue;
+            },
+        };
+
+        // the board is read again so that a spectator never gets out of step
+        if let Ok(v) = client.spectate(id) {
+            board.board = v.board;
+            println!("{}", board.print());
+        }
+    }
+}
@@ -3,12 +3,20 @@ extern crate dotenv;
 use dotenv::dotenv;
 
 use mongodb::{
-    bson::doc,
+    bson::{doc, from_document, oid::ObjectId, to_bson, to_document, Document},
+    options::{FindOneAndUpdateOptions, ReturnDocument},
     sync::{Client, Collection},
 };
-use crate::models::board_model::Board;
+use crate::models::{
+    board_model::Board,
+    event_model::{GameEvent, GameEventEntry},
+};
 use super::db_type::*;
 
+// times an event is retried when other events keep being appended to the same board
+const APPEND_RETRIES: usize = 16;
+
+#[derive(Clone)]
 pub struct BoardRepo {
     col: Collection<Board>,
 }
@@ -21,7 +29,7 @@ impl BoardRepo {
         dotenv().ok();
         let uri = match env::var(ENV_MONGODB) {
             Ok(v) => v.to_string(),
-            Err(_) => format!("Error loading env variable"),
+            Err(_) => "Error loading env variable".to_string(),
         };
         println!("{}", uri);
 
@@ -34,19 +42,13 @@ impl BoardRepo {
     // add a board into mongodb
     pub fn create_board(&self, new_board: Board) -> bool {
 
-        match self.get_board(&new_board) {
-            Some(_) => return false,
-            None => (),
-        };
+        if self.get_board(&new_board).is_some() { return false };
 
         let board = self.col
             .insert_one(new_board, None)
             .ok();
 
-        match board {
-            Some(_) => true,
-            None => false,
-        }
+        board.is_some()
     }
 
     // delete a board from mongodb
@@ -56,20 +58,59 @@ impl BoardRepo {
             "player_1": board_info.player_1.replace("_", " "),
             "player_2": board_info.player_2.replace("_", " "),
             "mode": board_info.mode.clone(),
-            "difficulty": board_info.difficulty.clone(),
-            "width": board_info.width.clone(),
-            "height": board_info.height.clone(),
+            "difficulty": board_info.difficulty,
+            "width": board_info.width,
+            "height": board_info.height,
         };
 
-        match self.col.delete_one(filter, None).ok() {
-            Some(_) => true,
-            None => false,
-        }
+        self.col.delete_one(filter, None).ok().is_some()
     }
     
-    // update a board from mongodb
+    /*
+    Save a board over the stored one with the same id, in place so that it never goes missing.
+    The event log is left alone, events are only ever added to it by append_event.
+     */
     pub fn update_board(&self, board_info: &Board) -> bool {
-        self.delete_board(board_info) && self.create_board(board_info.clone())
+
+        let mut fields = match to_document(board_info) {
+            Ok(d) => d,
+            Err(_) => return false,
+        };
+        fields.remove("events");
+
+        let filter = doc! {"id": board_info.id.clone()};
+        let update = doc! {"$set": fields};
+        match self.col.update_one(filter, update, None) {
+            Ok(res) => res.matched_count == 1,
+            Err(_) => false,
+        }
+    }
+
+    /*
+    Add an event to the end of the event log of a board, numbered one after the last.
+    The event only goes in while the log still has the length it was numbered against,
+    so two events published at the same time never share a number or overwrite each other.
+    Returns the stored board with its new log, None if the board is gone.
+     */
+    pub fn append_event(&self, id: &str, event: &GameEvent) -> Option<(Board, GameEventEntry)> {
+
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+
+        for _ in 0..APPEND_RETRIES {
+            let len = self.get_board_by_id(id)?.events.len() as i64;
+            let entry = GameEventEntry { seq: len + 1, event: event.clone() };
+            let filter = doc! {"id": id, "events": {"$size": len}};
+            let update = doc! {"$push": {"events": to_bson(&entry).ok()?}};
+
+            match self.col.find_one_and_update(filter, update, options.clone()) {
+                Ok(Some(b)) => return Some((b, entry)),
+                Ok(None) => continue,
+                Err(_) => return None,
+            };
+        }
+        None
     }
 
     // get a board from mongodb
@@ -79,22 +120,56 @@ impl BoardRepo {
             "player_1": board_info.player_1.replace("_", " "),
             "player_2": board_info.player_2.replace("_", " "),
             "mode": board_info.mode.clone(),
-            "difficulty": board_info.difficulty.clone(),
-            "width": board_info.width.clone(),
-            "height": board_info.height.clone(),
+            "difficulty": board_info.difficulty,
+            "width": board_info.width,
+            "height": board_info.height,
         };
         let board_detail = self.col
             .find_one(filter, None)
             .ok();
         
-        match board_detail {
-            Some(x) => x,
-            None => None
-        }
+        board_detail.unwrap_or_default()
 
         //return board_detail.unwrap();
     }
 
+    // get a board from mongodb by its id
+    pub fn get_board_by_id(&self, id: &str) -> Option<Board> {
+
+        let filter = doc! {"id": id};
+        let board_detail = self.col
+            .find_one(filter, None)
+            .ok();
+
+        board_detail.unwrap_or_default()
+    }
+
+    // get all boards from mongodb together with the key mongodb stored them under, boards stored by older versions may have no id
+    pub fn get_all_boards_by_key(&self) -> Option<Vec<(ObjectId, Board)>> {
+
+        let cursors = self.col
+            .clone_with_type::<Document>()
+            .find(None, None)
+            .ok();
+
+        cursors.map(|c| c
+                .filter_map(|doc| doc.ok())
+                .filter_map(|doc| match (doc.get_object_id("_id"), from_document(doc.clone())) {
+                    (Ok(key), Ok(board)) => Some((key, board)),
+                    _ => None,
+                })
+                .collect())
+    }
+
+    // write a board back under the key mongodb stored it with
+    pub fn replace_board_by_key(&self, key: &ObjectId, board: &Board) -> bool {
+
+        match self.col.replace_one(doc! {"_id": key}, board, None) {
+            Ok(res) => res.matched_count == 1,
+            Err(_) => false,
+        }
+    }
+
     // get all boards from mongodb
     pub fn get_all_boards(&self) -> Option<Vec<Board>> {
 
@@ -102,9 +177,6 @@ impl BoardRepo {
             .find(None, None)
             .ok();
 
-        match cursors {
-            Some(c) => Some(c.map(|doc| doc.unwrap()).collect()),
-            None => None,
-        }
+        cursors.map(|c| c.map(|doc| doc.unwrap()).collect())
     }
 }
\ No newline at end of file
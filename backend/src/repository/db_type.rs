@@ -3,6 +3,7 @@ pub const DB_NAME: &str = "connect4";
 pub const COL_USER: &str = "users";
 pub const COL_BOARD: &str = "boards";
 pub const COL_HIST: &str = "__hist__";
+pub const COL_INVITE: &str = "invites";
 
 // env vars
 pub const ENV_MONGODB: &str = "MONGOURI";
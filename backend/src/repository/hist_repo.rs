@@ -3,12 +3,13 @@ extern crate dotenv;
 use dotenv::dotenv;
 
 use mongodb::{
-    bson::doc,
+    bson::{doc, from_document, oid::ObjectId, to_bson, Document},
     sync::{Client, Collection},
 };
-use crate::models::board_model::{HistBoard, Board};
+use crate::models::board_model::{HistBoard, Board, MoveAnalysis};
 use super::db_type::*;
 
+#[derive(Clone)]
 pub struct HistRepo {
     col: Collection<HistBoard>,
 }
@@ -21,7 +22,7 @@ impl HistRepo {
         dotenv().ok();
         let uri = match env::var(ENV_MONGODB) {
             Ok(v) => v.to_string(),
-            Err(_) => format!("Error loading env variable"),
+            Err(_) => "Error loading env variable".to_string(),
         };
         println!("{}", uri);
 
@@ -39,16 +40,80 @@ impl HistRepo {
             .insert_one(hist, None)
             .ok();
 
-        match res {
-            Some(_) => true,
-            None => false,
+        res.is_some()
+    }
+
+    // add a finished game that was played elsewhere into hist record
+    pub fn insert_hist(&self, hist: &HistBoard) -> bool {
+
+        let res = self.col
+            .insert_one(hist.clone(), None)
+            .ok();
+
+        res.is_some()
+    }
+
+    // get every finished game together with the key mongodb stored it under, older games may have no board id
+    pub fn get_all_hist_by_key(&self) -> Option<Vec<(ObjectId, HistBoard)>> {
+
+        let cursors = self.col
+            .clone_with_type::<Document>()
+            .find(None, None)
+            .ok();
+
+        cursors.map(|c| c
+                .filter_map(|doc| doc.ok())
+                .filter_map(|doc| match (doc.get_object_id("_id"), from_document(doc.clone())) {
+                    (Ok(key), Ok(hist)) => Some((key, hist)),
+                    _ => None,
+                })
+                .collect())
+    }
+
+    // write a finished game back under the key mongodb stored it with, such as after its format changed
+    pub fn replace_hist(&self, key: &ObjectId, hist: &HistBoard) -> bool {
+
+        match self.col.replace_one(doc! {"_id": key}, hist, None) {
+            Ok(res) => res.matched_count == 1,
+            Err(_) => false,
         }
     }
 
+    // store the engine analysis of a finished game
+    pub fn set_analysis(&self, id: &str, analysis: &[MoveAnalysis]) -> bool {
+
+        let analysis = match to_bson(analysis) {
+            Ok(a) => a,
+            Err(_) => return false,
+        };
+        let filter = doc! {"board.id": id};
+        let update = doc! {
+            "$set": {
+                "analysis": analysis
+            }
+        };
+        let res = self.col
+            .update_one(filter, update, None)
+            .ok();
+
+        res.is_some()
+    }
+
+    // get the hist of a finished game by its board id
+    pub fn get_hist_by_id(&self, id: &str) -> Option<HistBoard> {
+
+        let filter = doc! {"board.id": id};
+        let hist = self.col
+            .find_one(filter, None)
+            .ok();
+
+        hist.unwrap_or_default()
+    }
+
     // get hist by user, * represents all
-    pub fn get_hist(&self, user: &String, side: &str) -> Option<Vec<HistBoard>> {
+    pub fn get_hist(&self, user: &str, side: &str) -> Option<Vec<HistBoard>> {
 
-        match user.clone().as_str() {
+        match user {
             "*" => {
 
                 if side == "player_2" {
@@ -59,23 +124,17 @@ impl HistRepo {
                     .find(None, None)
                     .ok();
 
-                match cursors {
-                    Some(c) => Some(c.map(|doc| doc.unwrap()).collect()),
-                    None => None,
-                }
+                cursors.map(|c| c.map(|doc| doc.unwrap()).collect())
             },
             _ => {
                 let filter = doc! {
-                    format!("board.{}", side.clone()).as_str(): user.clone()
+                    format!("board.{}", side).as_str(): user
                 };
                 let cursors = self.col
                     .find(filter, None)
                     .ok();
 
-                match cursors {
-                    Some(c) => Some(c.map(|doc| doc.unwrap()).collect()),
-                    None => None,
-                }
+                cursors.map(|c| c.map(|doc| doc.unwrap()).collect())
             },
         }
     }
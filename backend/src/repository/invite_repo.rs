@@ -0,0 +1,111 @@
+use std::env;
+extern crate dotenv;
+use dotenv::dotenv;
+
+use chrono::prelude::*;
+use mongodb::{
+    bson::doc,
+    sync::{Client, Collection},
+};
+use crate::models::invite_model::{Invitation, InviteStatus};
+use super::db_type::*;
+
+#[derive(Clone)]
+pub struct InviteRepo {
+    col: Collection<Invitation>,
+}
+
+impl InviteRepo {
+
+    // initialize a mongodb repo with a collection of invitations
+    pub fn init() -> Self {
+
+        dotenv().ok();
+        let uri = match env::var(ENV_MONGODB) {
+            Ok(v) => v.to_string(),
+            Err(_) => "Error loading env variable".to_string(),
+        };
+
+        let client = Client::with_uri_str(uri).unwrap();
+        let db = client.database(DB_NAME);
+        let col: Collection<Invitation> = db.collection(COL_INVITE);
+        InviteRepo { col }
+    }
+
+    // add an invitation into mongodb
+    pub fn create_invite(&self, invite: &Invitation) -> bool {
+
+        let res = self.col
+            .insert_one(invite.clone(), None)
+            .ok();
+
+        res.is_some()
+    }
+
+    // get an invitation from mongodb by its id
+    pub fn get_invite(&self, id: &str) -> Option<Invitation> {
+
+        let filter = doc! {"id": id};
+        let invite = self.col
+            .find_one(filter, None)
+            .ok();
+
+        invite.unwrap_or_default()
+    }
+
+    /*
+    Move an invitation from one status to another, the board id is only known once accepted.
+    Only succeeds while the invitation still has the status it is moved from,
+    so that it cannot be answered twice at the same time.
+     */
+    pub fn set_status(&self, id: &str, from: InviteStatus, to: InviteStatus, board_id: &str) -> bool {
+
+        let filter = doc! {
+            "id": id,
+            "status": from.as_str()
+        };
+        let update = doc! {
+            "$set": {
+                "status": to.as_str(),
+                "board_id": board_id
+            }
+        };
+
+        match self.col.update_one(filter, update, None) {
+            Ok(res) => res.modified_count == 1,
+            Err(_) => false,
+        }
+    }
+
+    // mark every pending invitation past its deadline as expired
+    pub fn expire_invites(&self) -> bool {
+
+        let filter = doc! {
+            "status": InviteStatus::Pending.as_str(),
+            "expires_at": { "$lt": Utc::now().timestamp() }
+        };
+        let update = doc! {
+            "$set": {
+                "status": InviteStatus::Expired.as_str()
+            }
+        };
+        let res = self.col
+            .update_many(filter, update, None)
+            .ok();
+
+        res.is_some()
+    }
+
+    // get invitations of a user, side is either "from" or "to"
+    pub fn get_invites(&self, user: &str, side: &str) -> Option<Vec<Invitation>> {
+
+        let filter = doc! {
+            side: user
+        };
+        let cursors = self.col
+            .find(filter, None)
+            .ok();
+
+        cursors.map(|c| c.filter_map(Result::ok).collect())
+    }
+}
@@ -2,3 +2,4 @@ pub mod user_repo;
 pub mod db_type;
 pub mod board_repo;
 pub mod hist_repo;
+pub mod invite_repo;
@@ -9,6 +9,7 @@ use mongodb::{
 use crate::models::user_model::User;
 use super::db_type::*;
 
+#[derive(Clone)]
 pub struct UserRepo {
     col: Collection<User>,
 }
@@ -21,7 +22,7 @@ impl UserRepo {
         dotenv().ok();
         let uri = match env::var(ENV_MONGODB) {
             Ok(v) => v.to_string(),
-            Err(_) => format!("Error loading env variable"),
+            Err(_) => "Error loading env variable".to_string(),
         };
         println!("{}", uri);
 
@@ -34,30 +35,24 @@ impl UserRepo {
     // add a user into mongodb
     pub fn create_user(&self, new_user: User) -> bool {
 
-        match self.get_user(&new_user.name) {
-            Some(_) => return false,
-            None => (),
-        };
+        if self.get_user(&new_user.name).is_some() { return false };
 
         let user = self.col
             .insert_one(new_user, None)
             .ok();
         
-        match user {
-            Some(_) => true,
-            None => false,
-        }
+        user.is_some()
     }
 
     // get a user from mongodb
-    pub fn get_user(&self, name: &String) -> Option<User> {
+    pub fn get_user(&self, name: &str) -> Option<User> {
 
         let filter = doc! {"name": name.replace("_", " ")};
         let user_detail = self.col
             .find_one(filter, None)
             .ok();
         
-        return user_detail.unwrap();
+        user_detail.unwrap()
     }
 
     // get all users from mongodb
@@ -67,14 +62,11 @@ impl UserRepo {
             .find(None, None)
             .ok();
         
-        match cursors {
-            Some(c) => Some(c.map(|doc| doc.unwrap()).collect()),
-            None => None,
-        }
+        cursors.map(|c| c.map(|doc| doc.unwrap()).collect())
     }
 
     // verify user password
-    pub fn verify_pwd(&self, name: &String, pwd: &String) -> bool {
+    pub fn verify_pwd(&self, name: &str, pwd: &String) -> bool {
 
         match self.get_user(name) {
             Some(user) => argon2::verify_encoded(user.pwd.as_str(), pwd.as_bytes()).unwrap(),
@@ -88,7 +80,7 @@ impl UserRepo {
         if name.eq("*") { return true;}
 
         let mut new_score = match self.get_user(name) {
-            Some(user) => user.score.clone(),
+            Some(user) => user.score,
             None => return false,
         };
         new_score += delta;
@@ -105,9 +97,6 @@ impl UserRepo {
             .update_one(filter, update, None)
             .ok();
 
-        match res {
-            Some(_) => true,
-            None => false,
-        }
+        res.is_some()
     }
 }
\ No newline at end of file
@@ -0,0 +1,192 @@
+use crate::{
+    models::{
+        analysis_model::{best_moves, AnalyzeResponse, Outcome},
+        board_model::{Board, HistBoard, MoveAnalysis, MoveVerdict, SearchStop},
+        general_model::GeneralStatus,
+    },
+    repository::hist_repo::HistRepo,
+};
+
+pub const ANALYSIS_DEPTH: i64 = 6;
+pub const HINT_DEPTH: i64 = 6;
+const HINT_FALLBACK_DEPTH: i64 = 2;
+
+/*
+Suggest a move for the player to move together with its score, from the side of that player,
+and the line the engine expects to follow.
+ */
+pub fn hint(board: &Board, depth: i64, threads: usize) -> (i64, i64, Vec<i64>) {
+
+    let sign = if board.get_next_player() == board.player_1 { 1 } else { -1 };
+    let result = board.search(depth, threads);
+    (result.best_move, result.score * sign, result.pv)
+}
+
+/*
+Suggest a move like hint, but give up once 'stop' is set.
+A search stopped by its deadline is replaced by a shallow one, nothing is suggested once cancelled.
+ */
+pub fn hint_until(board: &Board, depth: i64, threads: usize, stop: &SearchStop) -> Option<(i64, i64, Vec<i64>)> {
+
+    let sign = if board.get_next_player() == board.player_1 { 1 } else { -1 };
+    let mut result = board.search_until(depth, threads, stop);
+    if stop.is_cancelled() {
+        return None;
+    }
+    if stop.is_set() {
+        result = board.search(HINT_FALLBACK_DEPTH.min(depth), 1);
+    }
+    Some((result.best_move, result.score * sign, result.pv))
+}
+
+/*
+Let the engine look at a position given as a position string.
+The outcome is only called a draw when the search reached the end of every line,
+otherwise a score of 0 just means the engine could not see who wins.
+ */
+pub fn solve(position: &str, depth: i64, threads: usize) -> Result<AnalyzeResponse, String> {
+
+    let mut board = Board::new(0, 0, "x".to_owned(), "o".to_owned(), vec![], depth);
+    board.set_position(position)?;
+
+    let side = board.get_next_player();
+    let search = board.search(depth, threads);
+    let score = if side == board.player_1 { search.score } else { -search.score };
+    let empty = board.width * board.height - board.move_count();
+    let outcome = if score > 0 {
+        Outcome::Win
+    } else if score < 0 {
+        Outcome::Loss
+    } else if depth >= empty {
+        Outcome::Draw
+    } else { Outcome::Unknown };
+
+    Ok(AnalyzeResponse {
+        status: GeneralStatus::success(),
+        position: board.to_position_string(),
+        side_to_move: side,
+        outcome,
+        best_moves: best_moves(&search),
+        search,
+    })
+}
+
+// compare a move with the best one by the outcome each of them leads to
+fn verdict(score: i64, best_score: i64) -> MoveVerdict {
+    if score == best_score {
+        MoveVerdict::Best
+    } else if score < 0 && best_score >= 0 {
+        MoveVerdict::Blunder
+    } else if best_score > 0 && score <= 0 {
+        MoveVerdict::Mistake
+    } else {
+        MoveVerdict::Inaccuracy
+    }
+}
+
+/*
+Run the engine over every position of a finished game and judge the move played in it.
+Each position gets a full search, which also scores every move that could have been played there.
+ */
+pub fn analyze(hist: &HistBoard) -> Result<Vec<MoveAnalysis>, String> {
+
+    let mut board = hist.start_board()?;
+    let mut analysis: Vec<MoveAnalysis> = vec![];
+
+    for m in &hist.board.moves {
+        let sign = if board.get_next_player() == board.player_1 { 1 } else { -1 };
+        let search = board.search(ANALYSIS_DEPTH, 1);
+        let score = match search.root_scores.iter().find(|r| r.col == m.col) {
+            Some(r) => r.score * sign,
+            None => return Err(format!("Move {} does not fit on the board.", m.ply)),
+        };
+        let best_score = search.score * sign;
+        // the played move counts as best when it is as good as the one the search picked
+        let best_col = if score == best_score { m.col } else { search.best_move };
+
+        analysis.push(MoveAnalysis {
+            ply: m.ply,
+            player: m.player.clone(),
+            col: m.col,
+            score,
+            best_col,
+            best_score,
+            verdict: verdict(score, best_score),
+        });
+        board.perform_move(m.col, m.player.clone());
+    }
+    Ok(analysis)
+}
+
+/*
+Analyse a game that went into history and store the analysis with it.
+The engine workers run this once nobody is waiting for a computer reply.
+ */
+pub fn analyze_stored(hist: &HistRepo, board_id: &str) -> Result<(), String> {
+
+    let game = match hist.get_hist_by_id(board_id) {
+        Some(h) => h,
+        None => return Err("Game is not in history.".to_owned()),
+    };
+    let analysis = analyze(&game)?;
+    match hist.set_analysis(board_id, &analysis) {
+        true => Ok(()),
+        false => Err("Database not connected.".to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // alice stacks three in the first column, bob looks away and alice takes the win
+    fn game(moves: &[i64]) -> HistBoard {
+        let mut board = Board::new(7, 6, "alice".to_owned(), "bob".to_owned(), vec![false; 4], ANALYSIS_DEPTH);
+        for col in moves {
+            let player = board.get_next_player();
+            board.record_move(*col, player, None);
+        }
+        HistBoard::new(board, "alice".to_owned())
+    }
+
+    #[test]
+    fn verdicts_follow_the_outcome_given_away() {
+        assert_eq!(verdict(3, 3), MoveVerdict::Best);
+        assert_eq!(verdict(2, 5), MoveVerdict::Inaccuracy);
+        assert_eq!(verdict(-5, -2), MoveVerdict::Inaccuracy);
+        assert_eq!(verdict(0, 4), MoveVerdict::Mistake);
+        assert_eq!(verdict(-4, 0), MoveVerdict::Blunder);
+        assert_eq!(verdict(-4, 4), MoveVerdict::Blunder);
+    }
+
+    #[test]
+    fn missing_the_block_is_a_blunder_and_the_win_is_best() {
+        let analysis = analyze(&game(&[0, 1, 0, 1, 0, 6, 0])).unwrap();
+        assert_eq!(analysis.len(), 7);
+
+        let missed = &analysis[5];
+        assert_eq!((missed.player.as_str(), missed.col, missed.best_col), ("bob", 6, 0));
+        assert!(missed.score < 0);
+        assert_eq!(missed.verdict, MoveVerdict::Blunder);
+
+        let win = &analysis[6];
+        assert_eq!((win.player.as_str(), win.col, win.best_col), ("alice", 0, 0));
+        assert!(win.score > 0);
+        assert_eq!(win.verdict, MoveVerdict::Best);
+    }
+
+    #[test]
+    fn letting_a_win_go_is_a_mistake() {
+        let analysis = analyze(&game(&[0, 1, 0, 1, 0, 6, 5])).unwrap();
+        let missed = &analysis[6];
+        assert_eq!(missed.best_col, 0);
+        assert_eq!(missed.verdict, MoveVerdict::Mistake);
+    }
+
+    #[test]
+    fn move_off_the_board_cannot_be_analysed() {
+        let mut hist = game(&[0, 1]);
+        hist.board.moves[1].col = 9;
+        assert!(analyze(&hist).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+use std::time::Instant;
+
+use serde::{Serialize, Deserialize};
+
+use crate::models::board_model::Board;
+
+// positions the benchmark searches, from the start of a game to a crowded small board
+const BENCH_POSITIONS: [(&str, &str, i64); 4] = [
+    ("start", "7x6 TTTT 7/7/7/7/7/7 x", 8),
+    ("middlegame", "7x6 TTTT 7/7/7/3o3/2xx3/1oxxo2 o", 8),
+    ("toot", "7x6 TOOT 7/7/7/7/3x3/2oxo2 x", 7),
+    ("small", "5x4 TTTT 5/1o3/1xo2/oxxx1 o", 10),
+];
+
+// positions counted below one move of a perft
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PerftMove {
+    pub col: i64,
+    pub nodes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PerftReport {
+    pub position: String,
+    pub depth: i64,
+    pub nodes: u64,
+    pub moves: Vec<PerftMove>,
+    pub ms: f64,
+    pub nps: f64,
+}
+
+// one depth of the search of a benchmark position
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BenchDepth {
+    pub depth: i64,
+    pub nodes: i64,
+    pub ms: f64,  // Time to search to this depth from scratch.
+    pub nps: f64,
+    pub best_move: i64,
+    pub score: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BenchPosition {
+    pub name: String,
+    pub position: String,
+    pub depths: Vec<BenchDepth>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BenchReport {
+    pub positions: Vec<BenchPosition>,
+    pub nodes: i64,
+    pub ms: f64,
+    pub nps: f64,
+}
+
+fn nodes_per_second(nodes: f64, ms: f64) -> f64 {
+    if ms > 0.0 { nodes * 1000.0 / ms } else { 0.0 }
+}
+
+/*
+Run perft on a position, the positions below every move are counted on their own
+so that a wrong count can be narrowed down to a move.
+ */
+pub fn perft(position: &str, depth: i64) -> Result<PerftReport, String> {
+
+    let mut board = Board::new(0, 0, "x".to_owned(), "o".to_owned(), vec![], 1);
+    board.set_position(position)?;
+
+    let start = Instant::now();
+    let player = board.get_next_player();
+    let mut moves: Vec<PerftMove> = vec![];
+    if depth > 0 && !board.is_terminal() {
+        for col in board.ordered_moves() {
+            let mut b = board.clone();
+            b.perform_move(col, player.clone());
+            moves.push(PerftMove { col, nodes: b.perft(depth - 1) });
+        }
+        moves.sort_by_key(|m| m.col);
+    }
+    let ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let nodes = match depth > 0 {
+        true => moves.iter().map(|m| m.nodes).sum(),
+        false => 1,
+    };
+    Ok(PerftReport {
+        position: board.to_position_string(),
+        depth,
+        nodes,
+        moves,
+        ms,
+        nps: nodes_per_second(nodes as f64, ms),
+    })
+}
+
+/*
+Search every benchmark position on one thread to each depth up to its own, or up to
+'max_depth' when given, and time how long every depth takes from scratch.
+The search is deterministic on one thread, so the node counts only change with the engine.
+ */
+pub fn bench(max_depth: Option<i64>) -> BenchReport {
+
+    let mut report = BenchReport {
+        positions: vec![],
+        nodes: 0,
+        ms: 0.0,
+        nps: 0.0,
+    };
+
+    for (name, position, depth) in BENCH_POSITIONS {
+        let mut board = Board::new(0, 0, "x".to_owned(), "o".to_owned(), vec![], 1);
+        board.set_position(position).unwrap();
+
+        let mut depths: Vec<BenchDepth> = vec![];
+        for d in 1..=max_depth.unwrap_or(depth) {
+            let start = Instant::now();
+            let result = board.search(d, 1);
+            let ms = start.elapsed().as_secs_f64() * 1000.0;
+
+            report.nodes += result.nodes;
+            report.ms += ms;
+            depths.push(BenchDepth {
+                depth: d,
+                nodes: result.nodes,
+                ms,
+                nps: nodes_per_second(result.nodes as f64, ms),
+                best_move: result.best_move,
+                score: result.score,
+            });
+        }
+        report.positions.push(BenchPosition {
+            name: name.to_owned(),
+            position: position.to_owned(),
+            depths,
+        });
+    }
+    report.nps = nodes_per_second(report.nodes as f64, report.ms);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bench_positions_are_valid() {
+        for (name, position, depth) in BENCH_POSITIONS {
+            let mut board = Board::new(0, 0, "x".to_owned(), "o".to_owned(), vec![], 1);
+            assert_eq!(board.set_position(position), Ok(()), "{}", name);
+            assert_eq!(board.to_position_string(), position, "{}", name);
+            assert!(!board.is_terminal(), "{}", name);
+            assert!(depth > 0, "{}", name);
+        }
+    }
+
+    #[test]
+    fn perft_counts_every_line_of_the_empty_board() {
+        let start = "7x6 TTTT 7/7/7/7/7/7 x";
+        for (depth, nodes) in [(0, 1), (1, 7), (2, 49), (3, 343), (4, 2401)] {
+            assert_eq!(perft(start, depth).unwrap().nodes, nodes);
+        }
+        // only the seven games filling a single column lose their last move
+        let report = perft(start, 7).unwrap();
+        assert_eq!(report.nodes, 823_536);
+        assert_eq!(report.moves.len(), 7);
+        assert!(report.moves.iter().all(|m| m.nodes == 823_536 / 7));
+    }
+
+    #[test]
+    fn perft_stops_at_the_end_of_the_game() {
+        // x connects by dropping into the fourth column
+        let report = perft("7x6 TTTT 7/7/7/3x3/3x3/oo1x2o x", 2).unwrap();
+        let win = report.moves.iter().find(|m| m.col == 3).unwrap();
+        assert_eq!(win.nodes, 0);
+        assert_eq!(report.moves.len(), 7);
+        assert_eq!(report.nodes, 6 * 7);
+        assert!(perft("7x6 TTTT 7/7/7/7/7/7 q", 1).is_err());
+    }
+}
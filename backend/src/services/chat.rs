@@ -0,0 +1,83 @@
+use chrono::prelude::*;
+
+use crate::{
+    models::{
+        chat_model::*,
+        event_model::GameEvent,
+    },
+    repository::board_repo::BoardRepo,
+};
+use super::game_hub::GameHub;
+
+// words that are starred out of chat messages
+const BLOCKED_WORDS: [&str; 8] = ["fuck", "shit", "bitch", "bastard", "asshole", "dick", "cunt", "damn"];
+
+// replace every blocked word with as many stars as it has letters, punctuation around it does not matter
+fn clean(text: &str) -> String {
+
+    text.split_whitespace()
+        .map(|word| {
+            let letters: String = word.chars()
+                .filter(|c| c.is_alphabetic())
+                .collect::<String>()
+                .to_lowercase();
+            match BLOCKED_WORDS.iter().any(|w| letters == *w) {
+                true => "*".repeat(word.chars().count()),
+                false => word.to_owned(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/*
+Add a chat message to a running game and send it to everyone watching.
+Messages have to be short and not empty, a sender has to wait a moment between two messages
+and may not repeat themselves right away. Spectators may only chat when allowed to.
+ */
+pub fn post_chat(db: &BoardRepo, hub: &GameHub, board_id: &str, sender: &str, text: &str, spectator_chat: bool) -> Result<ChatMessage, &'static str> {
+
+    let mut board = match db.get_board_by_id(board_id) {
+        Some(b) => b,
+        None => return Err("Board does not exist or game is over."),
+    };
+
+    let spectator = sender != board.player_1 && sender != board.player_2;
+    if spectator && !spectator_chat {
+        return Err("Spectators may not chat in this game.");
+    }
+
+    let text = clean(text);
+    if text.is_empty() {
+        return Err("Message is empty.");
+    }
+    if text.chars().count() > MAX_CHAT_LEN {
+        return Err("Message is too long.");
+    }
+
+    let now = Utc::now().timestamp_millis();
+    match board.chat().iter().rev().find(|m| m.sender.eq(sender)) {
+        Some(m) if now - m.date < CHAT_COOLDOWN_MS => return Err("You are sending messages too fast."),
+        Some(m) if m.text == text && now - m.date < CHAT_REPEAT_MS => return Err("You already sent this message."),
+        _ => (),
+    };
+
+    let message = ChatMessage {
+        sender: sender.to_owned(),
+        text,
+        date: now,
+        spectator,
+    };
+    let entry = hub.publish(db, &mut board, GameEvent::Chat {
+        sender: message.sender.clone(),
+        text: message.text.clone(),
+        date: now,
+        spectator,
+    });
+
+    // the event log is where the chat is kept, a message that did not get into it is lost
+    match entry.seq {
+        0 => Err("Database not connected."),
+        _ => Ok(message),
+    }
+}
@@ -0,0 +1,441 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{mpsc::{self, Sender}, Arc, Condvar, Mutex},
+    thread,
+    time::Duration,
+};
+
+use chrono::prelude::*;
+use rand::seq::SliceRandom;
+
+use crate::{
+    models::{
+        analysis_model::best_moves,
+        board_model::{Board, EndReason, SearchStop},
+        config_model::AppConfig,
+        engine_model::{EngineJob, JobState},
+        event_model::GameEvent,
+    },
+    repository::{board_repo::BoardRepo, hist_repo::HistRepo, user_repo::UserRepo},
+};
+use super::{analysis::{analyze_stored, hint_until, HINT_DEPTH}, game_end::GameEnd, game_hub::GameHub};
+
+const DRAW_STR: &str = "^";
+const FALLBACK_DEPTH: i64 = 2;
+const JOB_KEEP_MS: i64 = 10 * 60 * 1000;
+// finished games waiting for analysis, more are left for the migrate command to catch up on
+const MAX_QUEUED_ANALYSES: usize = 64;
+
+/*
+Choose the move of the computer and its score. The computer does not always search as deep
+as its difficulty allows, and picks any of the equally good moves it finds.
+When the search was stopped by its deadline a shallow search decides instead,
+nothing is played once cancelled or when there is no move to play.
+ */
+pub fn computer_move(board: &Board, threads: usize, stop: &SearchStop) -> Option<(i64, i64)> {
+
+    // add a mutation possibility
+    let (a, b, c) = (
+        board.difficulty,
+        board.difficulty - 2,
+        board.difficulty - 4
+    );
+    let depth = [
+        a, a, a, a, a, a, a,
+        b, b,
+        c
+    ].choose(&mut rand::thread_rng()).copied().unwrap_or(a).max(1);
+
+    let mut result = board.search_until(depth, threads, stop);
+    if stop.is_cancelled() {
+        return None;
+    }
+    // a search that finished in time is kept even when the deadline has passed since
+    if stop.is_set() {
+        result = board.search(FALLBACK_DEPTH.min(depth), 1);
+    }
+    let best_move = best_moves(&result).choose(&mut rand::thread_rng()).copied()?;
+    Some((best_move, result.score))
+}
+
+// play the chosen move of the computer on the board and tell everyone watching
+pub fn record_computer_move(db: &BoardRepo, hub: &GameHub, b: &mut Board, col: i64, score: i64) {
+
+    let next_player = b.get_next_player();
+    b.record_move(col, next_player.clone(), Some(score));
+    b.press_clock(&next_player, Utc::now().timestamp_millis());
+    b.last_activity_ms = Utc::now().timestamp_millis();
+    let (r, c) = (b.last_row, b.last_col);
+    hub.publish(db, b, GameEvent::MoveMade { player: next_player, row: r, col: c });
+}
+
+// a queued job together with the position the computer has to answer
+struct Reply {
+    job_id: String,
+    board: Board,
+    stop: SearchStop,  // Without a deadline yet, the time only starts once a worker picks the job.
+}
+
+// a hint somebody is waiting for, the move, its score and the expected line go back through 'answer'
+struct HintQuery {
+    board: Board,
+    stop: SearchStop,
+    answer: Sender<Option<(i64, i64, Vec<i64>)>>,
+}
+
+// what the workers are asked to do, replies and hints go before analyses since somebody is waiting for them
+enum Task {
+    Reply(Reply),
+    Hint(HintQuery),
+    Analysis(String),  // Id of a finished game to go over.
+}
+
+// work out a hint within the time a computer reply gets, unless the asker gave up already
+fn answer_hint(query: HintQuery, threads: usize, timeout_ms: u64) {
+
+    if query.stop.is_cancelled() {
+        return;
+    }
+    let stop = SearchStop {
+        deadline: SearchStop::after(timeout_ms).deadline,
+        ..query.stop
+    };
+    let _ = query.answer.send(hint_until(&query.board, HINT_DEPTH, threads, &stop));
+}
+
+/*
+Works out computer replies on a few dedicated threads so that no request waits for the engine.
+Jobs are answered in the order they came in, each gets a limited time to search and can be
+cancelled while it waits or runs. Finished jobs are kept for a while so that they can be polled.
+Hints are worked out on the same threads, and finished games are analysed whenever nobody is waiting.
+ */
+#[derive(Clone, Default)]
+pub struct EnginePool {
+    queue: Arc<(Mutex<VecDeque<Task>>, Condvar)>,
+    jobs: Arc<Mutex<HashMap<String, (EngineJob, SearchStop)>>>,
+}
+
+impl EnginePool {
+
+    // start the worker threads, jobs can be queued before but wait until then
+    pub fn start(&self, config: AppConfig, db: BoardRepo, hist: HistRepo, users: UserRepo, hub: GameHub) {
+
+        for _ in 0..config.engine_workers.max(1) {
+            let (pool, config, db, hist, users, hub) = (self.clone(), config.clone(), db.clone(), hist.clone(), users.clone(), hub.clone());
+            thread::spawn(move || loop {
+                match pool.next_task() {
+                    Task::Reply(reply) => {
+                        let end = GameEnd { db: &db, hist: &hist, users: &users, hub: &hub, engine: &pool };
+                        pool.run(reply, &config, &end);
+                    },
+                    Task::Hint(query) => answer_hint(query, config.search_threads, config.engine_timeout_ms),
+                    Task::Analysis(board_id) => {
+                        if let Err(msg) = analyze_stored(&hist, &board_id) {
+                            println!("Analysis of game {} failed: {}", board_id, msg);
+                        }
+                    },
+                };
+            });
+        }
+    }
+
+    // queue a computer reply to the board, which has to be saved with the computer to move
+    pub fn submit(&self, board: &Board) -> String {
+
+        let job = EngineJob::new(board.id.clone());
+        let stop = SearchStop::default();
+        {
+            let now = Utc::now().timestamp_millis();
+            let mut jobs = self.jobs.lock().unwrap();
+            jobs.retain(|_, (j, _)| j.state.is_pending() || now - j.finished_ms < JOB_KEEP_MS);
+            jobs.insert(job.id.clone(), (job.clone(), stop.clone()));
+        }
+
+        let (queue, ready) = &*self.queue;
+        queue.lock().unwrap().push_back(Task::Reply(Reply {
+            job_id: job.id.clone(),
+            board: board.clone(),
+            stop,
+        }));
+        ready.notify_one();
+        job.id
+    }
+
+    /*
+    Suggest a move for the player to move on one of the workers and wait for it.
+    Nothing is suggested when no worker answered within 'wait_ms', the hint is given up on then.
+     */
+    pub fn hint(&self, board: &Board, wait_ms: u64) -> Option<(i64, i64, Vec<i64>)> {
+
+        let (answer, hint) = mpsc::channel();
+        let stop = SearchStop::default();
+        let (queue, ready) = &*self.queue;
+        queue.lock().unwrap().push_back(Task::Hint(HintQuery {
+            board: board.clone(),
+            stop: stop.clone(),
+            answer,
+        }));
+        ready.notify_one();
+
+        match hint.recv_timeout(Duration::from_millis(wait_ms)) {
+            Ok(h) => h,
+            Err(_) => {
+                stop.cancel();
+                None
+            },
+        }
+    }
+
+    // queue the analysis of a game that just went into history, false if too many are waiting already
+    pub fn submit_analysis(&self, board_id: &str) -> bool {
+
+        let (queue, ready) = &*self.queue;
+        let mut queue = queue.lock().unwrap();
+        if queue.iter().filter(|t| matches!(t, Task::Analysis(_))).count() >= MAX_QUEUED_ANALYSES {
+            return false;
+        }
+        queue.push_back(Task::Analysis(board_id.to_owned()));
+        ready.notify_one();
+        true
+    }
+
+    pub fn job(&self, id: &String) -> Option<EngineJob> {
+        self.jobs.lock().unwrap().get(id).map(|(j, _)| j.clone())
+    }
+
+    // whether the computer is still working out a reply on the board
+    pub fn pending(&self, board_id: &String) -> bool {
+        self.jobs.lock().unwrap().values()
+            .any(|(j, _)| j.board_id.eq(board_id) && j.state.is_pending())
+    }
+
+    // give up on every reply pending on the board, the game moved on without it
+    pub fn cancel_board(&self, board_id: &String) {
+
+        let now = Utc::now().timestamp_millis();
+        for (job, stop) in self.jobs.lock().unwrap().values_mut() {
+            if job.board_id.eq(board_id) && job.state.is_pending() {
+                stop.cancel();
+                job.state = JobState::Cancelled;
+                job.finished_ms = now;
+            }
+        }
+    }
+
+    fn next_task(&self) -> Task {
+
+        let (queue, ready) = &*self.queue;
+        let mut queue = queue.lock().unwrap();
+        loop {
+            let next = match queue.iter().position(|t| !matches!(t, Task::Analysis(_))) {
+                Some(i) => queue.remove(i),
+                None => queue.pop_front(),
+            };
+            match next {
+                Some(task) => return task,
+                None => queue = ready.wait(queue).unwrap(),
+            };
+        }
+    }
+
+    // move a job along, a job cancelled in the meantime stays cancelled
+    fn set_job(&self, id: &String, update: impl FnOnce(&mut EngineJob)) -> bool {
+
+        match self.jobs.lock().unwrap().get_mut(id) {
+            Some((job, _)) if job.state.is_pending() => {
+                update(job);
+                if !job.state.is_pending() {
+                    job.finished_ms = Utc::now().timestamp_millis();
+                }
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn run(&self, task: Reply, config: &AppConfig, end: &GameEnd) {
+
+        let (db, hub) = (end.db, end.hub);
+
+        if !self.set_job(&task.job_id, |j| j.state = JobState::Running) {
+            return;
+        }
+        let stop = SearchStop {
+            deadline: SearchStop::after(config.engine_timeout_ms).deadline,
+            ..task.stop
+        };
+
+        let (col, score) = match computer_move(&task.board, config.search_threads, &stop) {
+            Some(m) => m,
+            None => {
+                let state = if stop.is_cancelled() { JobState::Cancelled } else { JobState::Failed };
+                self.set_job(&task.job_id, |j| j.state = state);
+                return;
+            },
+        };
+        let timed_out = stop.is_set();
+
+        // the game may have ended or moved on while the engine was thinking, checked again under
+        // the jobs lock right before saving so that a takeback or resignation cancelling the job
+        // cannot slip in between, and a board that is already over is never written to.
+        // the job is settled under the same lock, so once a reply was saved it is never called cancelled
+        let (mut b, winner) = {
+            let mut jobs = self.jobs.lock().unwrap();
+            let pending = matches!(jobs.get(&task.job_id), Some((j, _)) if j.state.is_pending());
+            let mut b = match db.get_board_by_id(&task.board.id) {
+                Some(b) if pending && !stop.is_cancelled()
+                    && b.end_reason.is_none() && !b.is_terminal()
+                    && b.move_count() == task.board.move_count()
+                    && b.to_position_string() == task.board.to_position_string() => b,
+                _ => {
+                    drop(jobs);
+                    self.set_job(&task.job_id, |j| j.state = JobState::Cancelled);
+                    return;
+                },
+            };
+            record_computer_move(db, hub, &mut b, col, score);
+            if !db.update_board(&b) {
+                drop(jobs);
+                self.set_job(&task.job_id, |j| j.state = JobState::Failed);
+                return;
+            }
+
+            let winner = match (b._has_winner(), b.is_draw()) {
+                ((true, winner), _) => winner,
+                (_, true) => DRAW_STR.to_owned(),
+                _ => "".to_owned(),
+            };
+            if let Some((job, _)) = jobs.get_mut(&task.job_id) {
+                job.state = if timed_out { JobState::TimedOut } else { JobState::Done };
+                job.cmput_row = b.last_row;
+                job.cmput_col = b.last_col;
+                job.score = score;
+                job.winner = winner.clone();
+                job.finished_ms = Utc::now().timestamp_millis();
+            }
+            (b, winner)
+        };
+
+        match winner.as_str() {
+            "" => (),
+            DRAW_STR => end.end_game(&mut b, &winner, EndReason::Draw),
+            _ => end.end_game(&mut b, &winner, EndReason::Connected),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    // alice opened in the middle, the computer is to move
+    fn board(difficulty: i64) -> Board {
+        let mut board = Board::new(7, 6, "alice".to_owned(), "*".to_owned(), vec![false; 4], difficulty);
+        board.record_move(3, "alice".to_owned(), None);
+        board
+    }
+
+    #[test]
+    fn cancelled_search_plays_nothing() {
+        let stop = SearchStop::default();
+        stop.cancel();
+        assert_eq!(computer_move(&board(5), 1, &stop), None);
+    }
+
+    #[test]
+    fn finished_search_is_kept_after_the_deadline() {
+        // a search this small never looks at the clock, so it is not stopped
+        let stop = SearchStop { deadline: Some(Instant::now()), ..SearchStop::default() };
+        let (col, _) = computer_move(&board(1), 1, &stop).unwrap();
+        assert!(board(1).allows_move(&col));
+        assert!(!stop.is_set());
+    }
+
+    #[test]
+    fn stopped_search_falls_back_to_a_shallow_one() {
+        let stop = SearchStop::after(0);
+        let (col, _) = computer_move(&board(9), 1, &stop).unwrap();
+        assert!(board(9).allows_move(&col));
+        assert!(stop.is_set());
+    }
+
+    #[test]
+    fn finished_game_has_no_move() {
+        let mut b = board(1);
+        for col in [0, 4, 0, 5, 0, 6] {
+            let player = b.get_next_player();
+            b.record_move(col, player, None);
+        }
+        assert!(b.is_terminal());
+        assert_eq!(computer_move(&b, 1, &SearchStop::default()), None);
+    }
+
+    #[test]
+    fn cancelling_a_board_cancels_its_pending_replies() {
+        let pool = EnginePool::default();
+        let (b, other) = (board(1), board(1));
+        let job_id = pool.submit(&b);
+        let other_id = pool.submit(&other);
+        assert!(pool.pending(&b.id));
+        assert_eq!(pool.job(&job_id).unwrap().state, JobState::Queued);
+
+        pool.cancel_board(&b.id);
+        assert!(!pool.pending(&b.id));
+        assert_eq!(pool.job(&job_id).unwrap().state, JobState::Cancelled);
+        assert!(pool.job(&job_id).unwrap().finished_ms > 0);
+        assert_eq!(pool.job(&other_id).unwrap().state, JobState::Queued);
+    }
+
+    #[test]
+    fn cancelled_job_stays_cancelled() {
+        let pool = EnginePool::default();
+        let b = board(1);
+        let job_id = pool.submit(&b);
+        pool.cancel_board(&b.id);
+        assert!(!pool.set_job(&job_id, |j| j.state = JobState::Running));
+        assert_eq!(pool.job(&job_id).unwrap().state, JobState::Cancelled);
+    }
+
+    #[test]
+    fn replies_go_before_analyses() {
+        let pool = EnginePool::default();
+        assert!(pool.submit_analysis("finished"));
+        let job_id = pool.submit(&board(1));
+        assert!(matches!(pool.next_task(), Task::Reply(r) if r.job_id == job_id));
+        assert!(matches!(pool.next_task(), Task::Analysis(id) if id == "finished"));
+    }
+
+    #[test]
+    fn hint_is_answered_by_a_worker() {
+        let pool = EnginePool::default();
+        let worker = pool.clone();
+        thread::spawn(move || match worker.next_task() {
+            Task::Hint(query) => answer_hint(query, 1, 10 * 1000),
+            _ => panic!("expected a hint"),
+        });
+        let (col, _, pv) = pool.hint(&board(1), 10 * 1000).unwrap();
+        assert!(board(1).allows_move(&col));
+        assert_eq!(pv.first(), Some(&col));
+    }
+
+    #[test]
+    fn hint_nobody_answered_is_given_up() {
+        let pool = EnginePool::default();
+        assert_eq!(pool.hint(&board(1), 10), None);
+        match pool.next_task() {
+            Task::Hint(query) => assert!(query.stop.is_cancelled()),
+            _ => panic!("expected a hint"),
+        };
+    }
+
+    #[test]
+    fn analyses_waiting_are_bounded() {
+        let pool = EnginePool::default();
+        for i in 0..MAX_QUEUED_ANALYSES {
+            assert!(pool.submit_analysis(&i.to_string()));
+        }
+        assert!(!pool.submit_analysis("one too many"));
+    }
+}
@@ -0,0 +1,109 @@
+use std::cmp::max;
+
+use crate::{
+    models::{
+        board_model::{Board, EndReason},
+        event_model::GameEvent,
+    },
+    repository::{board_repo::BoardRepo, hist_repo::HistRepo, user_repo::UserRepo},
+};
+use super::{engine::EnginePool, game_hub::GameHub};
+
+// everything a finished game is handed to, the same instances Rocket manages
+pub struct GameEnd<'a> {
+    pub db: &'a BoardRepo,
+    pub hist: &'a HistRepo,
+    pub users: &'a UserRepo,
+    pub hub: &'a GameHub,
+    pub engine: &'a EnginePool,
+}
+
+/*
+How the scores change once the winner is known, as the gain of the winner and the loss of the loser.
+Giving up or walking away costs the loser ten times as much as losing otherwise,
+draws and archived games leave the scores alone. Every hint the winner took shrinks their gain.
+ */
+pub fn score_change(board: &Board, winner: &String, reason: &EndReason) -> Option<(i64, i64)> {
+
+    let penalty = match reason {
+        EndReason::Draw | EndReason::Archived => return None,
+        EndReason::Resigned | EndReason::Abandoned => 10,
+        EndReason::Connected | EndReason::Timeout => 1,
+    };
+    // the gain is split by the hints taken plus one, rounded up so that a win is always worth something
+    let hints = board.hints(winner);
+    let gain = max(1, (board.difficulty + hints) / (hints + 1));
+    Some((gain, -board.difficulty*penalty))
+}
+
+impl GameEnd<'_> {
+
+    /*
+    Move a finished board into history, settle the scores and tell everyone watching.
+    Any computer reply still pending is cancelled, the engine workers go over the game once it is in history.
+     */
+    pub fn end_game(&self, board: &mut Board, winner: &String, reason: EndReason) {
+
+        let change = score_change(board, winner, &reason);
+        // a computer reply still being worked out must not be played on the finished board
+        self.engine.cancel_board(&board.id);
+        board.end_reason = Some(reason.clone());
+        self.hub.publish(self.db, board, GameEvent::GameOver { winner: winner.clone(), reason });
+        self.db.delete_board(board);
+        if self.hist.push_hist(board, winner) && !board.moves.is_empty() {
+            self.engine.submit_analysis(&board.id);
+        }
+        if let Some((gain, loss)) = change {
+            self.users.add_score(winner, gain);
+            self.users.add_score(&board.opponent(winner), loss);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // alice beat the computer at the given difficulty
+    fn board(difficulty: i64) -> Board {
+        Board::new(7, 6, "alice".to_owned(), "*".to_owned(), vec![false; 4], difficulty)
+    }
+
+    #[test]
+    fn connecting_moves_the_difficulty_both_ways() {
+        let alice = "alice".to_owned();
+        assert_eq!(score_change(&board(5), &alice, &EndReason::Connected), Some((5, -5)));
+        assert_eq!(score_change(&board(5), &alice, &EndReason::Timeout), Some((5, -5)));
+    }
+
+    #[test]
+    fn giving_up_costs_ten_times_as_much() {
+        let alice = "alice".to_owned();
+        assert_eq!(score_change(&board(3), &alice, &EndReason::Resigned), Some((3, -30)));
+        assert_eq!(score_change(&board(3), &alice, &EndReason::Abandoned), Some((3, -30)));
+    }
+
+    #[test]
+    fn hints_shrink_the_gain_but_never_to_nothing() {
+        let alice = "alice".to_owned();
+        let mut b = board(5);
+        b.hints_1 = 1;
+        assert_eq!(score_change(&b, &alice, &EndReason::Connected), Some((3, -5)));
+        b.hints_1 = 3;
+        assert_eq!(score_change(&b, &alice, &EndReason::Connected), Some((2, -5)));
+
+        let mut b = board(1);
+        b.hints_1 = 1;
+        assert_eq!(score_change(&b, &alice, &EndReason::Connected), Some((1, -1)));
+        // the loser's hints do not matter
+        b.hints_1 = 0;
+        b.hints_2 = 3;
+        assert_eq!(score_change(&b, &alice, &EndReason::Connected), Some((1, -1)));
+    }
+
+    #[test]
+    fn draws_and_archived_games_leave_the_scores_alone() {
+        assert_eq!(score_change(&board(5), &"^".to_owned(), &EndReason::Draw), None);
+        assert_eq!(score_change(&board(5), &"".to_owned(), &EndReason::Archived), None);
+    }
+}
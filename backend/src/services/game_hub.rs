@@ -0,0 +1,146 @@
+use std::{
+    cmp::max,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use rocket::tokio::sync::broadcast;
+
+use crate::{
+    models::{
+        board_model::Board,
+        event_model::{GameEvent, GameEventEntry},
+    },
+    repository::board_repo::BoardRepo,
+};
+
+const CHANNEL_CAPACITY: usize = 64;
+
+// channels of users share the map with the channels of boards
+fn user_key(name: &str) -> String {
+    format!("user/{}", name)
+}
+
+// fans game events out to every connection watching a board
+#[derive(Clone, Default)]
+pub struct GameHub {
+    channels: Arc<Mutex<HashMap<String, broadcast::Sender<GameEventEntry>>>>,
+    spectators: Arc<Mutex<HashMap<String, i64>>>,
+}
+
+// counts a spectator of a board for as long as it is kept
+pub struct SpectatorGuard {
+    hub: GameHub,
+    board_id: String,
+}
+
+impl Drop for SpectatorGuard {
+    fn drop(&mut self) {
+        self.hub.count_spectator(&self.board_id, -1);
+    }
+}
+
+impl GameHub {
+
+    // start listening to the events of a board
+    pub fn subscribe(&self, board_id: &str) -> broadcast::Receiver<GameEventEntry> {
+
+        let mut channels = self.channels.lock().unwrap();
+        channels
+            .entry(board_id.to_owned())
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    // start listening to the events sent to a user, such as a game being started for them
+    pub fn subscribe_user(&self, name: &str) -> broadcast::Receiver<GameEventEntry> {
+        self.subscribe(&user_key(name))
+    }
+
+    // send an event to a user, it is not part of any game log
+    pub fn notify_user(&self, name: &str, event: GameEvent) {
+
+        let channels = self.channels.lock().unwrap();
+        if let Some(sender) = channels.get(&user_key(name)) {
+            let _ = sender.send(GameEventEntry { seq: 0, event });
+        }
+    }
+
+    // send an event to everyone watching a board without adding it to the event log
+    pub fn broadcast(&self, board_id: &str, event: GameEvent) {
+
+        let channels = self.channels.lock().unwrap();
+        if let Some(sender) = channels.get(board_id) {
+            let _ = sender.send(GameEventEntry { seq: 0, event });
+        }
+    }
+
+    // number of spectators currently watching a board
+    pub fn spectators(&self, board_id: &str) -> i64 {
+        match self.spectators.lock().unwrap().get(board_id) {
+            Some(count) => *count,
+            None => 0,
+        }
+    }
+
+    // start counting a spectator of a board, until the returned guard is dropped
+    pub fn watch(&self, board_id: &str) -> SpectatorGuard {
+        self.count_spectator(board_id, 1);
+        SpectatorGuard {
+            hub: self.clone(),
+            board_id: board_id.to_owned(),
+        }
+    }
+
+    fn count_spectator(&self, board_id: &str, delta: i64) {
+
+        let count = {
+            let mut spectators = self.spectators.lock().unwrap();
+            let count = spectators.entry(board_id.to_owned()).or_insert(0);
+            *count += delta;
+            let count = *count;
+            if count <= 0 {
+                spectators.remove(board_id);
+            }
+            count
+        };
+        self.broadcast(board_id, GameEvent::SpectatorsChanged { count: max(count, 0) });
+    }
+
+    /*
+    Append an event to the event log of the board and send it to everyone watching.
+    The event is stored straight away and the log of the board is refreshed with the stored one,
+    the caller still saves the rest of the board afterwards.
+    An event that cannot be stored is still sent, without a number.
+    The channel is closed once the game is over.
+     */
+    pub fn publish(&self, db: &BoardRepo, board: &mut Board, event: GameEvent) -> GameEventEntry {
+
+        let entry = match db.append_event(&board.id, &event) {
+            Some((stored, entry)) => {
+                board.events = stored.events;
+                entry
+            },
+            None => GameEventEntry { seq: 0, event },
+        };
+
+        let mut channels = self.channels.lock().unwrap();
+        if let Some(sender) = channels.get(&board.id) {
+            let _ = sender.send(entry.clone());
+        }
+
+        if matches!(entry.event, GameEvent::GameOver { .. }) {
+            channels.remove(&board.id);
+        }
+        entry
+    }
+
+    // publish an event outside of a move request
+    pub fn publish_by_id(&self, db: &BoardRepo, board_id: &str, event: GameEvent) -> bool {
+
+        match db.get_board_by_id(board_id) {
+            Some(mut b) => self.publish(db, &mut b, event).seq != 0,
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::sync::Mutex;
+
+use crate::models::lobby_model::{Challenge, QueueEntry};
+
+// open challenges and the auto-match queue, both only live as long as the server
+#[derive(Default)]
+pub struct Lobby {
+    challenges: Mutex<Vec<Challenge>>,
+    queue: Mutex<Vec<QueueEntry>>,
+}
+
+impl Lobby {
+
+    // post a challenge, a player can only have one open challenge at a time
+    pub fn post(&self, challenge: Challenge) {
+
+        let mut challenges = self.challenges.lock().unwrap();
+        challenges.retain(|c| c.creator != challenge.creator);
+        challenges.push(challenge);
+    }
+
+    pub fn open_challenges(&self) -> Vec<Challenge> {
+        self.challenges.lock().unwrap().clone()
+    }
+
+    // remove a challenge so that nobody else can accept it
+    pub fn take(&self, id: &String) -> Option<Challenge> {
+
+        let mut challenges = self.challenges.lock().unwrap();
+        challenges.iter().position(|c| c.id.eq(id)).map(|i| challenges.remove(i))
+    }
+
+    // withdraw a challenge, only its creator may do so
+    pub fn cancel(&self, id: &String, name: &String) -> bool {
+
+        let mut challenges = self.challenges.lock().unwrap();
+        let before = challenges.len();
+        challenges.retain(|c| !(c.id.eq(id) && c.creator.eq(name)));
+        challenges.len() != before
+    }
+
+    /*
+    Look for a waiting opponent in the queue.
+    The opponent is taken out of the queue when found, otherwise the player waits in the queue.
+     */
+    pub fn enqueue(&self, entry: QueueEntry) -> Option<QueueEntry> {
+
+        let mut queue = self.queue.lock().unwrap();
+        queue.retain(|e| e.name != entry.name);
+
+        match queue.iter().position(|e| e.matches(&entry)) {
+            Some(i) => Some(queue.remove(i)),
+            None => {
+                queue.push(entry);
+                None
+            },
+        }
+    }
+
+    // put a player taken out of the queue back where they were, without looking for a match
+    pub fn requeue(&self, entry: QueueEntry) {
+
+        let mut queue = self.queue.lock().unwrap();
+        queue.retain(|e| e.name != entry.name);
+        queue.insert(0, entry);
+    }
+
+    pub fn leave_queue(&self, name: &String) -> bool {
+
+        let mut queue = self.queue.lock().unwrap();
+        let before = queue.len();
+        queue.retain(|e| e.name.ne(name));
+        queue.len() != before
+    }
+
+    // a player who got a game stops looking for one
+    pub fn clear_player(&self, name: &String) {
+        self.challenges.lock().unwrap().retain(|c| c.creator.ne(name));
+        self.leave_queue(name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::lobby_model::GameSettings;
+
+    fn entry(name: &str, rating: i64, rating_range: i64) -> QueueEntry {
+        QueueEntry {
+            name: name.to_owned(),
+            rating,
+            rating_range,
+            settings: GameSettings { width: 7, height: 6, mode: vec![false; 4], time_control: None },
+        }
+    }
+
+    #[test]
+    fn players_within_both_ranges_are_matched() {
+        let lobby = Lobby::default();
+        assert!(lobby.enqueue(entry("alice", 1200, 100)).is_none());
+        let opponent = lobby.enqueue(entry("bob", 1290, 100)).unwrap();
+        assert_eq!(opponent.name, "alice");
+        assert!(!lobby.leave_queue(&"bob".to_owned()));
+    }
+
+    #[test]
+    fn both_players_must_accept_the_rating_difference() {
+        let lobby = Lobby::default();
+        assert!(lobby.enqueue(entry("alice", 1200, 50)).is_none());
+        assert!(lobby.enqueue(entry("bob", 1290, 400)).is_none());
+        assert_eq!(lobby.enqueue(entry("carol", 1400, 400)).unwrap().name, "bob");
+    }
+
+    #[test]
+    fn players_need_the_same_settings() {
+        let mut wide = entry("bob", 1200, 100);
+        wide.settings.width = 8;
+        assert!(!entry("alice", 1200, 100).matches(&wide));
+        assert!(!entry("alice", 1200, 100).matches(&entry("alice", 1200, 100)));
+    }
+
+    #[test]
+    fn requeued_player_waits_at_the_front() {
+        let lobby = Lobby::default();
+        assert!(lobby.enqueue(entry("alice", 1200, 100)).is_none());
+        lobby.requeue(entry("bob", 1210, 100));
+        assert_eq!(lobby.enqueue(entry("carol", 1205, 100)).unwrap().name, "bob");
+    }
+}
@@ -0,0 +1,95 @@
+use crate::models::board_model::Board;
+
+const EXPLORATION: f64 = 1.41;
+
+// one position of the search tree, wins are counted for the player who moved into it
+struct Node {
+    parent: Option<usize>,
+    col: i64,
+    player: String,
+    children: Vec<usize>,
+    untried: Vec<i64>,
+    visits: f64,
+    wins: f64,
+}
+
+// the child that is most worth looking into, balancing how well it did against how little it was tried
+fn select_child(nodes: &[Node], n: usize) -> usize {
+
+    let parent_visits = nodes[n].visits.ln();
+    nodes[n].children.iter()
+        .map(|c| (*c, nodes[*c].wins / nodes[*c].visits + EXPLORATION * (parent_visits / nodes[*c].visits).sqrt()))
+        .fold((n, f64::MIN), |best, (c, value)| if value > best.1 { (c, value) } else { best })
+        .0
+}
+
+/*
+Choose a move for the player to move with Monte Carlo tree search. Every iteration walks down
+the tree to a position not looked into yet and plays random moves from there until the game ends.
+The move tried most often is the best one. The position must not be over already.
+ */
+pub fn mcts_move(board: &Board, iterations: usize) -> i64 {
+
+    let mut nodes = vec![Node {
+        parent: None,
+        col: -1,
+        player: board.last_player.clone(),
+        children: vec![],
+        untried: board.available_moves(),
+        visits: 0.0,
+        wins: 0.0,
+    }];
+
+    for _ in 0..iterations.max(1) {
+        let mut b = board.clone();
+        let mut n = 0;
+
+        // selection
+        while nodes[n].untried.is_empty() && !nodes[n].children.is_empty() {
+            n = select_child(&nodes, n);
+            b.perform_move(nodes[n].col, nodes[n].player.clone());
+        }
+
+        // expansion, available_moves are shuffled so the untried moves come in random order
+        if let Some(col) = nodes[n].untried.pop() {
+            let player = b.get_next_player();
+            b.perform_move(col, player.clone());
+            nodes.push(Node {
+                parent: Some(n),
+                col,
+                player,
+                children: vec![],
+                untried: if b.is_terminal() { vec![] } else { b.available_moves() },
+                visits: 0.0,
+                wins: 0.0,
+            });
+            let child = nodes.len() - 1;
+            nodes[n].children.push(child);
+            n = child;
+        }
+
+        // playout
+        while !b.is_terminal() {
+            let player = b.get_next_player();
+            b.perform_move(b.available_moves()[0], player);
+        }
+        let winner = match b._has_winner() {
+            (true, w) => w,
+            (false, _) => "".to_owned(),
+        };
+
+        // backpropagation
+        loop {
+            nodes[n].visits += 1.0;
+            nodes[n].wins += if winner.is_empty() { 0.5 } else if winner == nodes[n].player { 1.0 } else { 0.0 };
+            match nodes[n].parent {
+                Some(p) => n = p,
+                None => break,
+            };
+        }
+    }
+
+    nodes[0].children.iter()
+        .fold((-1, -1.0), |best, c| if nodes[*c].visits > best.1 { (nodes[*c].col, nodes[*c].visits) } else { best })
+        .0
+}
@@ -0,0 +1,12 @@
+pub mod game_hub;
+pub mod ws_server;
+pub mod lobby;
+pub mod game_end;
+pub mod reaper;
+pub mod chat;
+pub mod analysis;
+pub mod engine;
+pub mod mcts;
+pub mod selfplay;
+pub mod bench;
+pub mod sessions;
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use chrono::prelude::*;
+use rocket::tokio::{task::spawn_blocking, time};
+
+use crate::{
+    models::{
+        board_model::EndReason,
+        config_model::{AppConfig, ReaperAction},
+    },
+    repository::{board_repo::BoardRepo, hist_repo::HistRepo, user_repo::UserRepo},
+};
+use super::{engine::EnginePool, game_end::GameEnd, game_hub::GameHub};
+
+/*
+Periodically finish games nobody is playing anymore.
+A player to move whose clock ran out loses on time. A game without a move for longer than
+the abandon timeout is lost by the player to move, or archived without a winner
+if configured so or if nobody has moved yet. A board that does not know when it was last
+played on gets the time of the pass instead and is only judged on a later one.
+ */
+pub async fn run(config: AppConfig, db: BoardRepo, hist: HistRepo, users: UserRepo, hub: GameHub, engine: EnginePool) {
+
+    let mut interval = time::interval(Duration::from_secs(config.reaper_interval_secs.max(1)));
+    loop {
+        interval.tick().await;
+        let (config, db, hist, users, hub, engine) = (config.clone(), db.clone(), hist.clone(), users.clone(), hub.clone(), engine.clone());
+        let _ = spawn_blocking(move || {
+            reap(&config, &GameEnd { db: &db, hist: &hist, users: &users, hub: &hub, engine: &engine })
+        }).await;
+    }
+}
+
+// go over the active boards once, returns how many games were finished
+pub fn reap(config: &AppConfig, end: &GameEnd) -> usize {
+
+    let db = end.db;
+    let boards = match db.get_all_boards() {
+        Some(b) => b,
+        None => return 0,
+    };
+    let now = Utc::now().timestamp_millis();
+    let timeout = config.abandon_timeout_secs as i64 * 1000;
+    let mut finished = 0;
+
+    for mut b in boards {
+        if let Some(loser) = b.flagged_player(now) {
+            let winner = b.opponent(&loser);
+            end.end_game(&mut b, &winner, EndReason::Timeout);
+            finished += 1;
+            continue;
+        }
+
+        // boards stored before activity was tracked have none, their clock starts now
+        if b.last_activity_ms == 0 {
+            b.last_activity_ms = now;
+            db.update_board(&b);
+            continue;
+        }
+        if now - b.last_activity_ms <= timeout {
+            continue;
+        }
+
+        let loser = b.get_next_player();
+        let archive = config.reaper_action == ReaperAction::Archive || b.move_count() == 0 || loser == "*";
+        match archive {
+            true => end.end_game(&mut b, &"".to_owned(), EndReason::Archived),
+            false => {
+                let winner = b.opponent(&loser);
+                end.end_game(&mut b, &winner, EndReason::Abandoned);
+            },
+        };
+        finished += 1;
+    }
+    finished
+}
@@ -0,0 +1,212 @@
+use std::time::Instant;
+
+use rand::seq::SliceRandom;
+
+use crate::models::{
+    analysis_model::best_moves,
+    board_model::{Board, SearchStop},
+};
+use super::{engine::computer_move, mcts::mcts_move};
+
+const ELO_Z: f64 = 1.96;
+
+// an engine configuration taking part in a self-play match
+#[derive(Debug, Clone, PartialEq)]
+pub enum EngineSpec {
+    Level(i64),  // Plays like the computer of the web app at a level from 1 to 5.
+    AlphaBeta(i64),  // Always searches to the given depth.
+    Mcts(usize),  // Monte Carlo tree search with the given number of playouts.
+}
+
+impl EngineSpec {
+
+    /*
+    Read an engine from "level:<1-5>", "ab:<depth>" or "mcts:<playouts>".
+    The engine only scores finished games, so there are no evaluation weights to choose from.
+     */
+    pub fn parse(spec: &str) -> Result<Self, String> {
+
+        let (kind, value) = match spec.split_once(':') {
+            Some(x) => x,
+            None => return Err(format!("Engine '{}' should look like kind:value.", spec)),
+        };
+        let value: i64 = match value.trim().parse() {
+            Ok(v) if v > 0 => v,
+            _ => return Err(format!("Engine '{}' needs a positive number.", spec)),
+        };
+        match kind.trim() {
+            "level" if value <= 5 => Ok(EngineSpec::Level(value)),
+            "level" => Err("Levels go from 1 to 5.".to_owned()),
+            "ab" => Ok(EngineSpec::AlphaBeta(value)),
+            "mcts" => Ok(EngineSpec::Mcts(value as usize)),
+            "weights" => Err("The engine has no evaluation function, so there are no weights to set.".to_owned()),
+            _ => Err(format!("Unknown engine '{}', use level, ab or mcts.", kind)),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            EngineSpec::Level(l) => format!("level:{}", l),
+            EngineSpec::AlphaBeta(d) => format!("ab:{}", d),
+            EngineSpec::Mcts(n) => format!("mcts:{}", n),
+        }
+    }
+
+    // the move of the engine for the player to move
+    pub fn choose_move(&self, board: &Board) -> i64 {
+        match self {
+            EngineSpec::Level(l) => {
+                let mut b = board.clone();
+                b.difficulty = l * 2 - 1;
+                computer_move(&b, 1, &SearchStop::default()).unwrap().0
+            },
+            EngineSpec::AlphaBeta(d) => best_moves(&board.search(*d, 1))
+                .choose(&mut rand::thread_rng()).copied().unwrap(),
+            EngineSpec::Mcts(n) => mcts_move(board, *n),
+        }
+    }
+}
+
+// the results of the first engine against the second one
+#[derive(Debug, Clone, Default)]
+pub struct MatchStats {
+    pub wins: i64,
+    pub draws: i64,
+    pub losses: i64,
+    pub moves: [i64; 2],
+    pub think_ms: [f64; 2],
+}
+
+impl MatchStats {
+
+    pub fn games(&self) -> i64 {
+        self.wins + self.draws + self.losses
+    }
+
+    // average time an engine took for a move, 0 is the first engine
+    pub fn avg_move_ms(&self, engine: usize) -> f64 {
+        match self.moves[engine] {
+            0 => 0.0,
+            n => self.think_ms[engine] / n as f64,
+        }
+    }
+
+    /*
+    Elo difference of the first engine over the second one together with the margin of
+    a 95% confidence interval, from the score of the games and how much it varies.
+    Infinite when one engine won every game.
+     */
+    pub fn elo(&self) -> (f64, f64) {
+
+        let n = self.games() as f64;
+        if n == 0.0 {
+            return (0.0, f64::INFINITY);
+        }
+        let score = (self.wins as f64 + self.draws as f64 / 2.0) / n;
+        let variance = (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2)) / n;
+        let margin = ELO_Z * (variance / n).sqrt();
+
+        let to_elo = |s: f64| -400.0 * (1.0 / s.clamp(0.0, 1.0) - 1.0).log10();
+        let (low, high) = (to_elo(score - margin), to_elo(score + margin));
+        (to_elo(score), (high - low) / 2.0)
+    }
+}
+
+/*
+Play one game between two engines on a fresh board, 'first' tells which engine plays first.
+Gives the winning engine, None for a draw, and adds the time each engine thought to 'stats'.
+ */
+pub fn play_game(engines: &[EngineSpec; 2], first: usize, width: i64, height: i64, mode: &[bool], stats: &mut MatchStats) -> Option<usize> {
+
+    let mut board = Board::new(width, height, "x".to_owned(), "o".to_owned(), mode.to_vec(), 1);
+    loop {
+        let player = board.get_next_player();
+        let engine = if player == board.player_1 { first } else { 1 - first };
+
+        let start = Instant::now();
+        let col = engines[engine].choose_move(&board);
+        stats.think_ms[engine] += start.elapsed().as_secs_f64() * 1000.0;
+        stats.moves[engine] += 1;
+
+        board.perform_move(col, player);
+        match board._has_winner() {
+            (true, winner) if winner == board.player_1 => return Some(first),
+            (true, _) => return Some(1 - first),
+            (false, _) => (),
+        };
+        if board.is_draw() {
+            return None;
+        }
+    }
+}
+
+/*
+Play a match of 'games' games between two engines on each size and mode, the engines take turns
+playing first. Every game is printed as it ends.
+ */
+pub fn run_match(engines: &[EngineSpec; 2], games: i64, sizes: &Vec<(i64, i64)>, modes: &Vec<Vec<bool>>) -> MatchStats {
+
+    let mut stats = MatchStats::default();
+    for (width, height) in sizes {
+        for mode in modes {
+            for g in 0..games {
+                let first = (g % 2) as usize;
+                let result = play_game(engines, first, *width, *height, mode, &mut stats);
+                let outcome = match result {
+                    Some(0) => { stats.wins += 1; format!("{} wins", engines[0].name()) },
+                    Some(_) => { stats.losses += 1; format!("{} wins", engines[1].name()) },
+                    None => { stats.draws += 1; "draw".to_owned() },
+                };
+                let mode_str: String = mode.iter().map(|o| if *o { 'O' } else { 'T' }).collect();
+                println!("Game {} on {}x{} {}: {} plays first, {}.", stats.games(), width, height, mode_str, engines[first].name(), outcome);
+            }
+        }
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(wins: i64, draws: i64, losses: i64) -> MatchStats {
+        MatchStats { wins, draws, losses, ..MatchStats::default() }
+    }
+
+    #[test]
+    fn even_score_is_no_elo_difference() {
+        let (elo, margin) = stats(5, 2, 5).elo();
+        assert!(elo.abs() < 1e-9);
+        assert!(margin > 0.0);
+        assert_eq!(stats(0, 4, 0).elo(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn elo_follows_the_score() {
+        // 75% is 400 * log10(3) points better
+        let (elo, margin) = stats(30, 0, 10).elo();
+        assert!((elo - 190.85).abs() < 0.01);
+        assert!(margin > 0.0 && margin < elo);
+        let (elo, _) = stats(10, 0, 30).elo();
+        assert!((elo + 190.85).abs() < 0.01);
+        assert!(stats(120, 0, 40).elo().1 < margin);
+    }
+
+    #[test]
+    fn one_sided_match_is_infinite() {
+        assert_eq!(stats(4, 0, 0).elo().0, f64::INFINITY);
+        assert_eq!(stats(0, 0, 4).elo().0, f64::NEG_INFINITY);
+        assert_eq!(stats(0, 0, 0).elo(), (0.0, f64::INFINITY));
+    }
+
+    #[test]
+    fn game_counts_the_moves_of_each_engine() {
+        let engines = [EngineSpec::AlphaBeta(1), EngineSpec::AlphaBeta(1)];
+        let mut stats = MatchStats::default();
+        play_game(&engines, 1, 5, 4, &[false; 4], &mut stats);
+        assert!(stats.moves[1] >= 4);
+        assert!(stats.moves[1] - stats.moves[0] <= 1 && stats.moves[1] >= stats.moves[0]);
+    }
+}
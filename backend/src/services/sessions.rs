@@ -0,0 +1,70 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use chrono::prelude::*;
+use rand::{distributions::Alphanumeric, Rng};
+
+// a day of play before the user has to log in again
+const TOKEN_LIFETIME_MS: i64 = 24 * 60 * 60 * 1000;
+const TOKEN_LEN: usize = 32;
+
+/*
+Tokens handed out when a user logs in. Connections that cannot send a password,
+such as the websocket and event streams, pass the token to be treated as that user.
+ */
+#[derive(Clone, Default)]
+pub struct Sessions {
+    tokens: Arc<Mutex<HashMap<String, (String, i64)>>>,  // Token to the user name and when it expires.
+}
+
+impl Sessions {
+
+    // give a token to a user who has just logged in
+    pub fn issue(&self, name: &str) -> String {
+
+        let now = Utc::now().timestamp_millis();
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(TOKEN_LEN)
+            .map(char::from)
+            .collect();
+
+        let mut tokens = self.tokens.lock().unwrap();
+        tokens.retain(|_, (_, expires)| *expires > now);
+        tokens.insert(token.clone(), (name.to_owned(), now + TOKEN_LIFETIME_MS));
+        token
+    }
+
+    // the user a token was given to, None if it is unknown or has expired
+    pub fn name_of(&self, token: &str) -> Option<String> {
+
+        let now = Utc::now().timestamp_millis();
+        match self.tokens.lock().unwrap().get(token) {
+            Some((name, expires)) if *expires > now => Some(name.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_names_its_user() {
+        let sessions = Sessions::default();
+        let token = sessions.issue("alice");
+        assert_eq!(sessions.name_of(&token), Some("alice".to_owned()));
+        assert_ne!(sessions.issue("alice"), token);
+    }
+
+    #[test]
+    fn unknown_token_is_rejected() {
+        let sessions = Sessions::default();
+        sessions.issue("alice");
+        assert_eq!(sessions.name_of(""), None);
+        assert_eq!(sessions.name_of("alice"), None);
+    }
+}
@@ -0,0 +1,187 @@
+use std::net::IpAddr;
+
+use rocket::{
+    futures::{SinkExt, StreamExt},
+    http::RawStr,
+    serde::json,
+    tokio::{
+        self,
+        net::{TcpListener, TcpStream},
+        sync::broadcast::error::RecvError,
+        task::spawn_blocking,
+    },
+};
+
+use tokio_tungstenite::{
+    accept_hdr_async,
+    tungstenite::{
+        handshake::server::{ErrorResponse, Request, Response},
+        http::StatusCode,
+        Message,
+    },
+};
+
+use crate::{
+    models::{config_model::AppConfig, event_model::GameEvent},
+    repository::board_repo::BoardRepo,
+};
+use super::{chat::post_chat, game_hub::GameHub, sessions::Sessions};
+
+/*
+Listen for websocket connections on ws://<address>:<port>/board/<id>/ws?token=<token>
+and push every event of that board to the connection until the game is over.
+The token comes from logging in. Players that pass one can send chat messages as text frames,
+everyone else is a spectator who only listens unless spectator chat is enabled,
+and never gets to see private events.
+Users waiting in the lobby connect to ws://<address>:<port>/lobby/<name>/ws?token=<token> instead,
+which is refused unless the token was given to that user.
+ */
+pub async fn serve(address: IpAddr, config: AppConfig, hub: GameHub, db: BoardRepo, sessions: Sessions) {
+
+    let listener = match TcpListener::bind((address, config.ws_port)).await {
+        Ok(l) => l,
+        Err(e) => {
+            println!("Websocket server failed to start: {}", e);
+            return;
+        }
+    };
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => { tokio::spawn(handle_connection(stream, config.spectator_chat, hub.clone(), db.clone(), sessions.clone())); },
+            Err(_) => continue,
+        };
+    }
+}
+
+// what a connection listens to
+enum Topic {
+    Board(String),
+    User(String),
+}
+
+// get the topic out of /board/<id>/ws or /lobby/<name>/ws
+fn topic_of(path: &str) -> Option<Topic> {
+
+    let parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match parts.as_slice() {
+        ["board", id, "ws"] if !id.is_empty() => Some(Topic::Board(id.to_string())),
+        ["lobby", name, "ws"] if !name.is_empty() => {
+            let name = RawStr::new(name).url_decode_lossy().replace("_", " ");
+            Some(Topic::User(name))
+        },
+        _ => None,
+    }
+}
+
+// get the logged in user out of the token in the query string, empty for spectators
+fn player_of(sessions: &Sessions, query: Option<&str>) -> String {
+
+    for pair in query.unwrap_or("").split('&') {
+        if let Some(("token", token)) = pair.split_once('=') {
+            return sessions.name_of(&RawStr::new(token).url_decode_lossy()).unwrap_or_default();
+        }
+    }
+    "".to_owned()
+}
+
+// check that the name belongs to one of the two players of the board
+async fn is_player(db: &BoardRepo, board_id: &str, player: &str) -> bool {
+
+    let (db, board_id, player) = (db.clone(), board_id.to_owned(), player.to_owned());
+    let board = spawn_blocking(move || db.get_board_by_id(&board_id)).await;
+    match board {
+        Ok(Some(b)) => player == b.player_1 || player == b.player_2,
+        _ => false,
+    }
+}
+
+// publish an event from a connection, blocking the database off the async workers
+async fn publish(hub: &GameHub, db: &BoardRepo, board_id: &str, event: GameEvent) {
+
+    let (hub, db, board_id) = (hub.clone(), db.clone(), board_id.to_owned());
+    let _ = spawn_blocking(move || hub.publish_by_id(&db, &board_id, event)).await;
+}
+
+// send a chat message from a connection, messages breaking the chat rules are dropped
+async fn chat(hub: &GameHub, db: &BoardRepo, board_id: &str, sender: &str, text: String, spectator_chat: bool) {
+
+    let (hub, db, board_id, sender) = (hub.clone(), db.clone(), board_id.to_owned(), sender.to_owned());
+    let _ = spawn_blocking(move || post_chat(&db, &hub, &board_id, &sender, &text, spectator_chat)).await;
+}
+
+async fn handle_connection(stream: TcpStream, spectator_chat: bool, hub: GameHub, db: BoardRepo, sessions: Sessions) {
+
+    let (mut topic, mut player) = (None, String::new());
+    // the handshake callback has to answer with the error response tungstenite expects
+    #[allow(clippy::result_large_err)]
+    let ws = accept_hdr_async(stream, |req: &Request, resp: Response| {
+        match topic_of(req.uri().path()) {
+            // only the user themselves may listen to what is sent to them in the lobby
+            Some(Topic::User(name)) if player_of(&sessions, req.uri().query()) != name => {
+                let mut err = ErrorResponse::new(Some("Please log in again.".to_owned()));
+                *err.status_mut() = StatusCode::UNAUTHORIZED;
+                Err(err)
+            },
+            Some(t) => {
+                topic = Some(t);
+                player = player_of(&sessions, req.uri().query());
+                Ok(resp)
+            },
+            None => {
+                let mut err = ErrorResponse::new(Some("Unknown websocket path.".to_owned()));
+                *err.status_mut() = StatusCode::NOT_FOUND;
+                Err(err)
+            },
+        }
+    }).await;
+
+    let (mut sink, mut source) = match ws {
+        Ok(ws) => ws.split(),
+        Err(_) => return,
+    };
+
+    let (board_id, mut events) = match topic {
+        Some(Topic::Board(id)) => (id.clone(), hub.subscribe(&id)),
+        Some(Topic::User(name)) => {
+            player = "".to_owned();
+            ("".to_owned(), hub.subscribe_user(&name))
+        },
+        None => return,
+    };
+
+    // everyone who is not playing on the board is a spectator
+    let spectating = !board_id.is_empty() && (player.is_empty() || !is_player(&db, &board_id, &player).await);
+    let _guard = match spectating {
+        true => Some(hub.watch(&board_id)),
+        false => None,
+    };
+    if !spectating && !board_id.is_empty() {
+        publish(&hub, &db, &board_id, GameEvent::OpponentJoined { player: player.clone() }).await;
+    }
+
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(e) if spectating && e.event.is_private() => continue,
+                Ok(e) => {
+                    let text = json::to_string(&e).unwrap();
+                    if sink.send(Message::Text(text)).await.is_err() {
+                        break;
+                    }
+                },
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            },
+            msg = source.next() => match msg {
+                Some(Ok(Message::Text(text))) if !player.is_empty() => {
+                    chat(&hub, &db, &board_id, &player, text, spectator_chat).await;
+                },
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+        }
+    }
+
+    let _ = sink.close().await;
+}
@@ -0,0 +1,283 @@
+use std::{
+    io::{stdout, Stdout, Write},
+    time::Duration,
+};
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{poll, read, Event, KeyCode, KeyEvent, KeyEventKind},
+    queue,
+    style::{Color, Print, PrintStyledContent, Stylize},
+    terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+use crate::{
+    models::board_model::{Board, SearchStop},
+    services::{
+        analysis::{hint, ANALYSIS_DEPTH},
+        engine::computer_move,
+    },
+};
+
+const DRAW_STR: &str = "**";
+const COMPUTER_PAUSE_MS: u64 = 400;
+const HISTORY_LINES: usize = 12;
+
+// computers are called "" when they play first and "*" when they play second, like host_game does
+fn is_computer(player: &String) -> bool {
+    player.is_empty() || player == "*"
+}
+
+fn display_name(board: &Board, player: &str) -> String {
+    match player {
+        "" if board.player_2 == "*" => "Computer 1".to_owned(),
+        "*" if board.player_1.is_empty() => "Computer 2".to_owned(),
+        "" | "*" => "Computer".to_owned(),
+        name => name.to_owned(),
+    }
+}
+
+// gives the terminal back the way it was, also when the game panics
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = queue!(stdout(), Show, LeaveAlternateScreen);
+        let _ = stdout().flush();
+        let _ = disable_raw_mode();
+    }
+}
+
+// what the screen shows besides the board
+struct Screen {
+    board: Board,
+    cursor: i64,
+    show_eval: bool,
+    eval: Option<(i64, i64, Vec<i64>)>,  // Best move, its score for the player to move and the expected line.
+    message: String,
+    winner: Option<String>,  // Set once the game is over, ** for a draw.
+}
+
+impl Screen {
+
+    fn disc(&self, cell: &String) -> PrintStyledContent<String> {
+
+        // a connect four game shows discs, the other modes show the letter of each player
+        let connect = self.board.mode.iter().all(|o| !o);
+        let (text, color) = if cell.eq(&self.board.player_1) {
+            (if connect { "●" } else { "T" }, Color::Red)
+        } else if cell.eq(&self.board.player_2) {
+            (if connect { "●" } else { "O" }, Color::Yellow)
+        } else {
+            ("·", Color::DarkGrey)
+        };
+        PrintStyledContent(text.to_owned().with(color))
+    }
+
+    fn draw(&self, out: &mut Stdout) -> std::io::Result<()> {
+
+        let b = &self.board;
+        let mode: String = b.mode.iter().map(|o| if *o { 'O' } else { 'T' }).collect();
+        let next = b.get_next_player();
+        queue!(out, Clear(ClearType::All), MoveTo(0, 0))?;
+        queue!(
+            out,
+            Print(format!("{} {}x{}   ", mode, b.width, b.height)),
+            PrintStyledContent(display_name(b, &b.player_1).with(Color::Red)),
+            Print(" vs "),
+            PrintStyledContent(display_name(b, &b.player_2).with(Color::Yellow)),
+        )?;
+
+        // the column selector sits above the board
+        let human_turn = self.winner.is_none() && !is_computer(&next);
+        queue!(out, MoveTo(2 + 2 * self.cursor as u16, 2))?;
+        if human_turn {
+            queue!(out, self.disc(&next))?;
+        }
+        for row in 0..b.height {
+            queue!(out, MoveTo(0, 3 + row as u16), Print("|"))?;
+            for col in 0..b.width {
+                queue!(out, Print(" "), self.disc(&b.board[row as usize][col as usize]))?;
+            }
+            queue!(out, Print(" |"))?;
+        }
+        let bottom = 3 + b.height as u16;
+        queue!(out, MoveTo(0, bottom), Print(format!("+{}+", "-".repeat(2 * b.width as usize + 1))))?;
+        let labels: Vec<String> = (1..=b.width).map(|c| (c % 10).to_string()).collect();
+        queue!(out, MoveTo(2, bottom + 1), Print(labels.join(" ")))?;
+
+        // move history, two moves to a line like the notation
+        let panel = 2 * b.width as u16 + 6;
+        queue!(out, MoveTo(panel, 2), Print("Moves"))?;
+        let lines: Vec<String> = b.moves.chunks(2).enumerate()
+            .map(|(i, pair)| {
+                let cols: Vec<String> = pair.iter().map(|m| (m.col + 1).to_string()).collect();
+                format!("{:>3}. {}", i + 1, cols.join(" "))
+            })
+            .collect();
+        for (i, line) in lines.iter().skip(lines.len().saturating_sub(HISTORY_LINES)).enumerate() {
+            queue!(out, MoveTo(panel, 3 + i as u16), Print(line))?;
+        }
+
+        // engine evaluation for the player to move
+        let engine = panel + 16;
+        queue!(out, MoveTo(engine, 2), Print("Engine"))?;
+        match (&self.eval, self.show_eval) {
+            (Some((col, score, pv)), true) => {
+                let verdict = if *score > 0 { "wins" } else if *score < 0 { "loses" } else { "holds" };
+                let line: Vec<String> = pv.iter().map(|m| (m + 1).to_string()).collect();
+                queue!(
+                    out,
+                    MoveTo(engine, 3), Print(format!("{} {}", display_name(b, &next), verdict)),
+                    MoveTo(engine, 4), Print(format!("best {}, score {:+}", col + 1, score)),
+                    MoveTo(engine, 5), Print(format!("line {}", line.join(" "))),
+                )?;
+            },
+            (_, true) => queue!(out, MoveTo(engine, 3), Print("-"))?,
+            (_, false) => queue!(out, MoveTo(engine, 3), Print("off"))?,
+        };
+
+        let status = match &self.winner {
+            Some(w) if w == DRAW_STR => "Draw.".to_owned(),
+            Some(w) => format!("{} wins!", display_name(b, w)),
+            None if is_computer(&next) => format!("{} is thinking...", display_name(b, &next)),
+            None => format!("{} to move.", display_name(b, &next)),
+        };
+        queue!(
+            out,
+            MoveTo(0, bottom + 3), Print(status),
+            MoveTo(0, bottom + 4), Print(&self.message),
+            MoveTo(0, bottom + 6), Print("left/right choose  enter drop  1-9 drop in column  u undo  e engine  q quit"),
+        )?;
+        out.flush()
+    }
+
+    // play a move for the player to move and see whether the game is over
+    fn play(&mut self, col: i64, score: Option<i64>) {
+
+        let player = self.board.get_next_player();
+        self.board.record_move(col, player, score);
+        self.winner = match self.board._has_winner() {
+            (true, winner) => Some(winner),
+            (false, _) if self.board.is_draw() => Some(DRAW_STR.to_owned()),
+            (false, _) => None,
+        };
+        self.evaluate();
+    }
+
+    fn evaluate(&mut self) {
+        self.eval = match self.show_eval && self.winner.is_none() {
+            true => Some(hint(&self.board, ANALYSIS_DEPTH.min(self.board.difficulty.max(1)), 1)),
+            false => None,
+        };
+    }
+
+    // take moves back until a human is to move again, the last move when both are human
+    fn undo(&mut self) {
+
+        let humans: Vec<String> = [&self.board.player_1, &self.board.player_2].iter()
+            .filter(|p| !is_computer(p))
+            .map(|p| p.to_string())
+            .collect();
+        let n = humans.iter()
+            .map(|h| self.board.takeback_len(h))
+            .filter(|n| *n > 0)
+            .min();
+        match n {
+            Some(n) => {
+                self.board.take_back(n);
+                self.winner = None;
+                self.message = format!("Took back {} move{}.", n, if n == 1 { "" } else { "s" });
+                self.evaluate();
+            },
+            None => self.message = "Nothing to undo.".to_owned(),
+        };
+    }
+
+    // handle a key of a human player, false to leave the game
+    fn key(&mut self, code: KeyCode) -> bool {
+
+        self.message = "".to_owned();
+        let human_turn = self.winner.is_none() && !is_computer(&self.board.get_next_player());
+        let drop = match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('u') => { self.undo(); None },
+            KeyCode::Char('e') => {
+                self.show_eval = !self.show_eval;
+                self.evaluate();
+                None
+            },
+            KeyCode::Left | KeyCode::Char('h') => { self.cursor = (self.cursor - 1).max(0); None },
+            KeyCode::Right | KeyCode::Char('l') => { self.cursor = (self.cursor + 1).min(self.board.width - 1); None },
+            KeyCode::Enter | KeyCode::Down | KeyCode::Char(' ') => Some(self.cursor),
+            KeyCode::Char(c) if c.is_ascii_digit() && c != '0' => Some(c.to_digit(10).unwrap() as i64 - 1),
+            _ => None,
+        };
+        match drop {
+            Some(col) if human_turn && self.board.allows_move(&col) => {
+                self.cursor = col;
+                self.play(col, None);
+            },
+            Some(_) if human_turn => self.message = "That column is full or off the board.".to_owned(),
+            Some(_) => (),
+            None => (),
+        };
+        true
+    }
+}
+
+/*
+Play a game full screen in the terminal, with every setup host_game plays: two humans,
+a human and the computer, or two computers. The board is left as the game ended.
+'on_change' sees the board after every move and undo, such as to save the game.
+Returns the winner like host_game, ** for a draw and None when the players left before the game was over.
+ */
+pub fn run(board: &mut Board, on_change: &mut dyn FnMut(&Board)) -> Result<Option<String>, String> {
+
+    let mut out = stdout();
+    enable_raw_mode().map_err(|e| format!("The terminal cannot be used: {}", e))?;
+    let _guard = TerminalGuard;
+    queue!(out, EnterAlternateScreen, Hide).map_err(|e| e.to_string())?;
+
+    let mut screen = Screen {
+        cursor: board.width / 2,
+        board: board.clone(),
+        show_eval: true,
+        eval: None,
+        message: "".to_owned(),
+        winner: None,
+    };
+    screen.evaluate();
+
+    loop {
+        screen.draw(&mut out).map_err(|e| e.to_string())?;
+        let next = screen.board.get_next_player();
+
+        // the computer moves on its own, a key can still stop or undo in between
+        let wait = match screen.winner.is_none() && is_computer(&next) {
+            true => Some(Duration::from_millis(COMPUTER_PAUSE_MS)),
+            false => None,
+        };
+        let ready = match wait {
+            Some(w) => poll(w).map_err(|e| e.to_string())?,
+            None => true,
+        };
+        if ready {
+            let played = screen.board.moves.len();
+            if let Event::Key(KeyEvent { code, kind: KeyEventKind::Press, .. }) = read().map_err(|e| e.to_string())? {
+                if !screen.key(code) {
+                    *board = screen.board;
+                    return Ok(screen.winner);
+                }
+            }
+            if screen.board.moves.len() != played {
+                on_change(&screen.board);
+            }
+            continue;
+        }
+
+        let (col, score) = computer_move(&screen.board, 1, &SearchStop::default()).unwrap();
+        screen.play(col, Some(score));
+        on_change(&screen.board);
+    }
+}
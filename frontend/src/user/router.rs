@@ -12,7 +12,7 @@ use web_sys::{
     Element, Event, HtmlDivElement, HtmlHeadingElement, HtmlImageElement, HtmlInputElement,
     MouseEvent,
 };
-use yew::{function_component, html, Callback, Html};
+use yew::{function_component, html, use_mut_ref, Callback, Html, Properties};
 use yew_router::{navigator, prelude::*};
 
 #[derive(Clone, Routable, PartialEq)]
@@ -37,6 +37,9 @@ enum UserRoute {
 
     #[at("/user/scoreboard")]
     UserScoreBoard,
+
+    #[at("/user/replay/:id")]
+    UserReplay { id: String },
 }
 
 fn get_input_value(element: &str) -> String {
@@ -48,6 +51,35 @@ fn get_input_value(element: &str) -> String {
         .value();
 }
 
+fn set_input_value(element: &str, value: &str) {
+    document()
+        .get_element_by_id(element)
+        .unwrap()
+        .dyn_into::<HtmlInputElement>()
+        .unwrap()
+        .set_value(value);
+}
+
+// wait until the computer has replied to a move, giving the finished engine job
+async fn wait_for_reply(job_id: &str) -> serde_json::Value {
+    let job_uri = format!("{}/engine/job/{}", BACKEND_URI, job_id);
+    loop {
+        sleep(Duration::from_millis(300)).await;
+        let response = reqwest_wasm::Client::new()
+            .get(&job_uri)
+            .send()
+            .await
+            .unwrap()
+            .json::<serde_json::Value>()
+            .await
+            .unwrap();
+        match response["job"]["state"].as_str() {
+            Some("queued") | Some("running") => continue,
+            _ => return response["job"].clone(),
+        }
+    }
+}
+
 fn set_heading_message(element: &str, message: &str) {
     let _ = document()
         .get_element_by_id(element)
@@ -243,6 +275,7 @@ fn user_play_computer() -> Html {
                         "Login failed! User password combination does not exist!",
                     );
                 } else {
+                    set_input_value("player-token", response["token"].as_str().unwrap_or(""));
                     set_Div_display("info-prompt", true);
                     set_Div_display("login-prompt", false);
                 }
@@ -428,7 +461,8 @@ fn user_play_computer() -> Html {
                         "mode": pattern,
                         "difficulty": difficulty
                     },
-                        "col": column}))
+                        "col": column,
+                        "token": get_input_value("player-token")}))
                 .send()
                 .await
                 .unwrap()
@@ -451,6 +485,12 @@ fn user_play_computer() -> Html {
                     .unwrap()
                     .set_attribute("src", "https://i.ibb.co/3z2fDPN/player1-fill.png");
 
+                // the computer replies on the server in its own time
+                let response = match response["job_id"].as_str() {
+                    Some(job_id) if !job_id.is_empty() => wait_for_reply(job_id).await,
+                    _ => response,
+                };
+
                 let cmput_row = response["cmput_row"].clone().to_string();
                 let cmput_column = response["cmput_col"].clone().to_string();
 
@@ -515,7 +555,8 @@ fn user_play_computer() -> Html {
                         "mode": pattern,
                         "difficulty": difficulty
                     },
-                        "col": -1}))
+                        "col": -1,
+                        "token": get_input_value("player-token")}))
                 .send()
                 .await
                 .unwrap()
@@ -542,6 +583,7 @@ fn user_play_computer() -> Html {
                     <div class="flex-container">
                         <input id="player-name" placeholder="Your name" style="margin-left: 0px" readonly=false/>
                         <input id="player-pwd" placeholder="Password" type = "password" readonly=false/>
+                        <input id="player-token" type="hidden" value=""/>
                         <button class="button" onclick={login_onclick}>{ "Start game" }</button>
                     </div>
                     <h5 id="login-msg" style="color: red; font-weight: normal">{ "" }</h5>
@@ -669,6 +711,8 @@ fn user_play_human() -> Html {
                         "Login failed! User password combination does not exist!",
                     );
                 } else {
+                    set_input_value("player-token1", response1["token"].as_str().unwrap_or(""));
+                    set_input_value("player-token2", response2["token"].as_str().unwrap_or(""));
                     set_Div_display("info-prompt", true);
                     set_Div_display("login-prompt", false);
                 }
@@ -759,6 +803,7 @@ fn user_play_human() -> Html {
                     if !response["status"]["success"].as_bool().unwrap() {
                         log!("Board generation failed");
                     } else {
+                        set_input_value("next-player", "1");
                         set_Div_display("info-prompt", false);
                         set_Div_display("column-prompt", true);
                         set_Div_display("giveup-button-prompt", true);
@@ -772,6 +817,11 @@ fn user_play_human() -> Html {
                     }
                 });
             } else {
+                // the player to move sends their password with the move
+                match response["board"]["next_player"] == response["board"]["player_1"] {
+                    true => set_input_value("next-player", "1"),
+                    false => set_input_value("next-player", "2"),
+                };
                 set_Div_display("info-prompt", false);
                 set_Div_display("column-prompt", true);
                 set_Div_display("giveup-button-prompt", true);
@@ -828,6 +878,7 @@ fn user_play_human() -> Html {
         let mode = get_input_value("board-mode");
 
         let pattern: Vec<bool> = mode.chars().map(|c| c.eq(&'O')).collect();
+        let next = get_input_value("next-player");
         // Make the move
         let make_move_uri = format!("{}/board/move", BACKEND_URI);
         wasm_bindgen_futures::spawn_local(async move {
@@ -851,7 +902,8 @@ fn user_play_human() -> Html {
                         "mode": pattern,
                         "difficulty": 1
                     },
-                        "col": column}))
+                        "col": column,
+                        "token": get_input_value(format!("player-token{}", next).as_str())}))
                 .send()
                 .await
                 .unwrap()
@@ -865,6 +917,10 @@ fn user_play_human() -> Html {
             } else {
                 let human_row = response["human_row"].clone().to_string();
                 let human_column = response["human_col"].clone().to_string();
+                match response["player"].as_bool().unwrap() {
+                    true => set_input_value("next-player", "1"),
+                    false => set_input_value("next-player", "2"),
+                };
                 if response["player"].as_bool().unwrap() == false {
                     let _ = document()
                         .get_element_by_id(
@@ -912,6 +968,7 @@ fn user_play_human() -> Html {
         let mode = get_input_value("board-mode");
 
         let pattern: Vec<bool> = mode.chars().map(|c| c.eq(&'O')).collect();
+        let next = get_input_value("next-player");
 
         wasm_bindgen_futures::spawn_local(async move {
             let client = reqwest_wasm::Client::new();
@@ -934,7 +991,8 @@ fn user_play_human() -> Html {
                         "mode": pattern,
                         "difficulty": 1
                     },
-                        "col": -1}))
+                        "col": -1,
+                        "token": get_input_value(format!("player-token{}", next).as_str())}))
                 .send()
                 .await
                 .unwrap()
@@ -970,6 +1028,9 @@ fn user_play_human() -> Html {
                         <input id="player-pwd1" placeholder="Password" type = "password" readonly=false/>
                         <input id="player-name2" placeholder="Your name" style="margin-left: 0px" readonly=false/>
                         <input id="player-pwd2" placeholder="Password" type = "password" readonly=false/>
+                        <input id="player-token1" type="hidden" value=""/>
+                        <input id="player-token2" type="hidden" value=""/>
+                        <input id="next-player" type="hidden" value="1"/>
                         <button class="button" onclick={login_onclick}>{ "Start game" }</button>
                     </div>
                     <h5 id="login-msg" style="color: red; font-weight: normal">{ "" }</h5>
@@ -1155,7 +1216,7 @@ fn user_game_history() -> Html {
                         if !response["status"]["success"].as_bool().unwrap() {
                             log!("Get game history failed!");
                         } else {
-                            let scoreboardprefix = "<table><tr><th>Player 1</th><th>Player 2</th><th>Date</th><th>Width</th><th>Height</th><th>Mode</th><th>Difficulty</th><th>Winner</th></tr>";
+                            let scoreboardprefix = "<table><tr><th>Player 1</th><th>Player 2</th><th>Date</th><th>Width</th><th>Height</th><th>Mode</th><th>Difficulty</th><th>Winner</th><th>Hints</th><th>Replay</th></tr>";
                             let scoreboardsuffix = "</table>";
                             let mut content = String::new();
 
@@ -1196,8 +1257,23 @@ fn user_game_history() -> Html {
                                     difficulty = String::from("N/A");
                                 }
 
+                                // hints taken by player 1 and player 2
+                                let hints = format!(
+                                    "{} / {}",
+                                    response["hist"][i]["board"]["hints_1"].as_i64().unwrap_or(0),
+                                    response["hist"][i]["board"]["hints_2"].as_i64().unwrap_or(0)
+                                );
+
+                                // games from before boards had an id cannot be replayed
+                                let replay = match response["hist"][i]["board"]["id"].as_str() {
+                                    Some(id) if !id.is_empty() => {
+                                        format!("<a href=\"/user/replay/{}\">Replay</a>", id)
+                                    }
+                                    _ => String::from("N/A"),
+                                };
+
                                 content += format!(
-                                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
                                     response["hist"][i]["board"]["player_1"].as_str().unwrap(),
                                     player2,
                                     response["hist"][i]["date"].as_str().unwrap(),
@@ -1206,6 +1282,8 @@ fn user_game_history() -> Html {
                                     pattern,
                                     difficulty,
                                     winner,
+                                    hints,
+                                    replay,
                                 )
                                 .as_str();
                             }
@@ -1252,6 +1330,183 @@ fn user_game_history() -> Html {
     }
 }
 
+// draw the board of one replay frame and describe the move that led to it
+fn show_replay_frame(replay: &serde_json::Value, ply: usize) {
+    let frames = replay["frames"].as_array().unwrap();
+    let frame = &frames[ply];
+    let player_1 = replay["hist"]["board"]["player_1"].as_str().unwrap();
+
+    let mut board_html = String::new();
+    for row in frame["board"].as_array().unwrap() {
+        let mut row_html = String::new();
+        for cell in row.as_array().unwrap() {
+            let src = if cell.as_str().unwrap() == " " {
+                "https://i.ibb.co/GFk3XzG/cell-empty.png"
+            } else if cell.as_str().unwrap() == player_1 {
+                "https://i.ibb.co/3z2fDPN/player1-fill.png"
+            } else {
+                "https://i.ibb.co/dgzxtqp/player2-fill.png"
+            };
+            row_html += format!("<img src=\"{}\" alt=\"Cell\" />", src).as_str();
+        }
+        board_html += format!("<div class=\"flex-container\">{}</div>", row_html).as_str();
+    }
+    let _ = document()
+        .get_element_by_id("replay-board")
+        .unwrap()
+        .dyn_into::<HtmlDivElement>()
+        .unwrap()
+        .set_inner_html(board_html.as_str());
+
+    if ply == 0 {
+        set_heading_message(
+            "replay-msg",
+            format!("Starting position, {} moves to go", frames.len() - 1).as_str(),
+        );
+    } else {
+        let mut player = frame["player"].as_str().unwrap();
+        if player == "*" {
+            player = "Computer";
+        }
+
+        // the engine verdict shows up once the game has been analysed
+        let mut verdict = String::new();
+        for analysis in replay["hist"]["analysis"].as_array().unwrap_or(&vec![]) {
+            if analysis["ply"].as_u64() != Some(ply as u64) {
+                continue;
+            }
+            verdict = match analysis["verdict"].as_str().unwrap() {
+                "best" => String::from(" (best move)"),
+                v => format!(
+                    " ({}, best was column {})",
+                    v,
+                    analysis["best_col"].as_i64().unwrap() + 1
+                ),
+            };
+        }
+
+        set_heading_message(
+            "replay-msg",
+            format!(
+                "Move {} of {}: {} played column {}{}",
+                ply,
+                frames.len() - 1,
+                player,
+                frame["col"].as_i64().unwrap() + 1,
+                verdict
+            )
+            .as_str(),
+        );
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct UserReplayProps {
+    pub id: String,
+}
+
+#[function_component(UserReplay)]
+fn user_replay(props: &UserReplayProps) -> Html {
+    let replay = use_mut_ref(|| serde_json::Value::Null);
+    let ply = use_mut_ref(|| 0 as usize);
+    let autoplay = use_mut_ref(|| false);
+
+    let replay_uri = format!("{}/hist/{}/replay", BACKEND_URI, props.id);
+    let replay_load = replay.clone();
+    wasm_bindgen_futures::spawn_local(async move {
+        let client = reqwest_wasm::Client::new();
+        let response = client
+            .get(replay_uri)
+            .send()
+            .await
+            .unwrap()
+            .json::<serde_json::Value>()
+            .await
+            .unwrap();
+
+        if !response["status"]["success"].as_bool().unwrap() {
+            log!("Get replay failed!");
+            set_heading_message("replay-msg", response["status"]["msg"].as_str().unwrap());
+        } else {
+            set_Div_display("replay-controls", true);
+            show_replay_frame(&response, 0);
+            *replay_load.borrow_mut() = response;
+        }
+    });
+
+    // move to the ply given by the current and the last ply
+    let step = |to: fn(usize, usize) -> usize| {
+        let (replay, ply, autoplay) = (replay.clone(), ply.clone(), autoplay.clone());
+        Callback::from(move |_event: MouseEvent| {
+            let replay = replay.borrow();
+            if replay.is_null() {
+                return;
+            }
+            let last = replay["frames"].as_array().unwrap().len() - 1;
+            let next = to(*ply.borrow(), last);
+            *ply.borrow_mut() = next;
+            *autoplay.borrow_mut() = false;
+            show_replay_frame(&replay, next);
+        })
+    };
+    let first_onclick = step(|_, _| 0);
+    let prev_onclick = step(|p, _| p.saturating_sub(1));
+    let next_onclick = step(|p, last| std::cmp::min(p + 1, last));
+    let last_onclick = step(|_, last| last);
+
+    let autoplay_onclick = Callback::from(move |_event: MouseEvent| {
+        if *autoplay.borrow() || replay.borrow().is_null() {
+            *autoplay.borrow_mut() = false;
+            return;
+        }
+        *autoplay.borrow_mut() = true;
+
+        let (replay, ply, autoplay) = (replay.clone(), ply.clone(), autoplay.clone());
+        wasm_bindgen_futures::spawn_local(async move {
+            let last = replay.borrow()["frames"].as_array().unwrap().len() - 1;
+            if *ply.borrow() == last {
+                *ply.borrow_mut() = 0;
+                show_replay_frame(&replay.borrow(), 0);
+            }
+            while *autoplay.borrow() && *ply.borrow() < last {
+                sleep(Duration::from_millis(800)).await;
+                if !*autoplay.borrow() {
+                    break;
+                }
+                let next = *ply.borrow() + 1;
+                *ply.borrow_mut() = next;
+                show_replay_frame(&replay.borrow(), next);
+            }
+            *autoplay.borrow_mut() = false;
+        });
+    });
+
+    html! {
+        <div class="sidenavpadding">
+            <div>
+                <h5 style="padding-top: 72px">{"Game replay"}</h5><br />
+            </div>
+
+            <div id="replay-controls" style="display: none">
+                <div class="flex-container">
+                    <button class="button" style="margin-left: 0px" onclick={first_onclick}>{ "First" }</button>
+                    <button class="button" onclick={prev_onclick}>{ "Previous" }</button>
+                    <button class="button" onclick={next_onclick}>{ "Next" }</button>
+                    <button class="button" onclick={last_onclick}>{ "Last" }</button>
+                    <button class="button" onclick={autoplay_onclick}>{ "Autoplay" }</button>
+                </div>
+            </div>
+
+            <h5 id="replay-msg" style="font-weight: normal">{ "" }</h5>
+
+            <div id="replay-board">
+            </div><br/>
+
+            <a href="/user/history">{"Back to game history"}</a>
+        </div>
+    }
+}
+
 #[function_component(User)]
 pub fn user_app() -> Html {
     html! {
@@ -1305,5 +1560,11 @@ fn switch(routes: UserRoute) -> Html {
             <UserScoreBoard />
             <SideBar />
         </div>},
+
+        UserRoute::UserReplay { id } => html! {
+        <div>
+            <UserReplay id={id} />
+            <SideBar />
+        </div>},
     }
 }